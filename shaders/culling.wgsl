@@ -0,0 +1,74 @@
+@group(0)
+@binding(0)
+var<uniform> frustum: FrustumUniform;
+
+@group(0)
+@binding(1)
+var<storage, read> instances: array<InstanceRaw>;
+
+@group(0)
+@binding(2)
+var<storage, read> instance_batches: array<u32>;
+
+@group(0)
+@binding(3)
+var<storage, read> batches: array<BatchBounds>;
+
+@group(0)
+@binding(4)
+var<storage, read_write> culled_instances: array<InstanceRaw>;
+
+@group(0)
+@binding(5)
+var<storage, read_write> draws: array<DrawIndexedIndirectArgs>;
+
+struct FrustumUniform {
+    // Normals point inside, the distance is stored in w
+    planes: array<vec4<f32>, 6>,
+    count: u32
+    }
+
+struct InstanceRaw {
+    model: mat4x4<f32>
+    }
+
+// Sphere around the batch's mesh, in the mesh's own space
+struct BatchBounds {
+    center: vec3<f32>,
+    radius: f32,
+    // First instance of the batch, visible instances are compacted behind it
+    offset: u32
+    }
+
+struct DrawIndexedIndirectArgs {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32
+    }
+
+@compute
+@workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= frustum.count {
+        return;
+        }
+
+    let instance = instances[index];
+    let batch = instance_batches[index];
+    let bounds = batches[batch];
+
+    // Instances have no scale, so the radius stays the same
+    let center = (instance.model * vec4<f32>(bounds.center, 1.0)).xyz;
+    for (var i = 0u; i < 6u; i += 1u) {
+        let plane = frustum.planes[i];
+        if dot(plane.xyz, center) + plane.w < - bounds.radius {
+            return;
+            }
+        }
+
+    let slot = atomicAdd(&draws[batch].instance_count, 1u);
+    culled_instances[bounds.offset + slot] = instance;
+    }
\ No newline at end of file
@@ -0,0 +1,110 @@
+@group(0)
+@binding(0)
+var<uniform> camera: CameraUniform;
+
+struct CameraUniform {
+    view_projection: mat4x4<f32>
+    }
+
+struct InstanceInput {
+    @location(5) row0: vec4<f32>,
+    @location(6) row1: vec4<f32>,
+    @location(7) row2: vec4<f32>,
+    @location(8) row3: vec4<f32>
+    }
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>
+    }
+
+struct BarycentricInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) barycentric: vec3<f32>
+    }
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    // Distance along the view direction, as w of a perspective projection
+    @location(2) view_depth: f32,
+    @location(3) @interpolate(flat) instance_index: u32,
+    @location(4) barycentric: vec3<f32>
+    }
+
+fn transform(position: vec3<f32>, tex_coords: vec2<f32>, instance: InstanceInput, instance_index: u32) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.row0,
+        instance.row1,
+        instance.row2,
+        instance.row3
+        );
+    let world_position = model_matrix * vec4<f32>(position, 1.0);
+
+    var out: VertexOutput;
+    out.world_position = world_position.xyz;
+    out.tex_coords = tex_coords;
+    out.clip_position = camera.view_projection * world_position;
+    out.view_depth = out.clip_position.w;
+    out.instance_index = instance_index;
+    out.barycentric = vec3<f32>(1.0);
+    return out;
+    }
+
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput, @builtin(instance_index) instance_index: u32) -> VertexOutput {
+    return transform(model.position, model.tex_coords, instance, instance_index);
+    }
+
+@vertex
+fn vs_barycentric(model: BarycentricInput, instance: InstanceInput, @builtin(instance_index) instance_index: u32) -> VertexOutput {
+    var out = transform(model.position, model.tex_coords, instance, instance_index);
+    out.barycentric = model.barycentric;
+    return out;
+    }
+
+// Flat normals from the screen space derivatives, as the vertices carry none
+@fragment
+fn fs_normals(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = normalize(cross(dpdy(in.world_position), dpdx(in.world_position)));
+    return vec4<f32>(normal * 0.5 + 0.5, 1.0);
+    }
+
+@fragment
+fn fs_tex_coords(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(fract(in.tex_coords), 0.0, 1.0);
+    }
+
+// Near surfaces are dark, and far ones fade towards white
+@fragment
+fn fs_depth(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(vec3<f32>(1.0 - exp(- in.view_depth * 0.2)), 1.0);
+    }
+
+@fragment
+fn fs_instance(in: VertexOutput) -> @location(0) vec4<f32> {
+    let hash = in.instance_index * 2654435761u;
+    let color = vec3<f32>(f32(hash & 255u), f32((hash >> 8u) & 255u), f32((hash >> 16u) & 255u)) / 255.0;
+    return vec4<f32>(color, 1.0);
+    }
+
+const WIREFRAME_COLOR: vec3<f32> = vec3<f32>(0.2, 1.0, 0.2);
+
+@fragment
+fn fs_wireframe(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(WIREFRAME_COLOR, 1.0);
+    }
+
+// Keeps only the pixels close to one of the edges, about a pixel wide
+@fragment
+fn fs_barycentric(in: VertexOutput) -> @location(0) vec4<f32> {
+    let width = fwidth(in.barycentric);
+    let edges = smoothstep(vec3<f32>(0.0), width * 1.5, in.barycentric);
+    let alpha = 1.0 - min(min(edges.x, edges.y), edges.z);
+    if alpha < 0.01 {
+        discard;
+        }
+    return vec4<f32>(WIREFRAME_COLOR, alpha);
+    }
\ No newline at end of file
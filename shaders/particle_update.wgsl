@@ -0,0 +1,81 @@
+@group(0)
+@binding(0)
+var<uniform> params: ParticleUniform;
+
+@group(0)
+@binding(1)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0)
+@binding(2)
+var<storage, read> emitters: array<Emitter>;
+
+struct ParticleUniform {
+    right: vec3<f32>,
+    size: f32,
+    up: vec3<f32>,
+    delta: f32,
+    gravity: vec3<f32>,
+    seed: u32,
+    count: u32
+    }
+
+struct Particle {
+    position: vec3<f32>,
+    age: f32,
+    velocity: vec3<f32>,
+    lifetime: f32,
+    emitter: u32
+    }
+
+struct Emitter {
+    position: vec3<f32>,
+    spread: f32,
+    velocity: vec3<f32>,
+    lifetime: f32
+    }
+
+// PCG hash, matches the CPU simulation
+fn hash(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+    }
+
+fn random(seed: u32) -> f32 {
+    return f32(hash(seed)) / 4294967295.0;
+    }
+
+@compute
+@workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= params.count {
+        return;
+        }
+
+    var particle = particles[index];
+    let previous_age = particle.age;
+    particle.age += params.delta;
+
+    // Negative ages wait for their turn to be emitted
+    if particle.age < 0.0 {
+        particles[index] = particle;
+        return;
+        }
+
+    if previous_age < 0.0 || particle.age >= particle.lifetime {
+        let emitter = emitters[particle.emitter];
+        let seed = hash(index ^ hash(params.seed));
+        let direction = vec3<f32>(random(seed), random(seed + 1u), random(seed + 2u)) * 2.0 - 1.0;
+
+        particle.age = particle.age % emitter.lifetime;
+        particle.lifetime = emitter.lifetime;
+        particle.position = emitter.position;
+        particle.velocity = emitter.velocity + direction * emitter.spread;
+        }
+
+    particle.velocity += params.gravity * params.delta;
+    particle.position += particle.velocity * params.delta;
+    particles[index] = particle;
+    }
\ No newline at end of file
@@ -0,0 +1,79 @@
+@group(0)
+@binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1)
+@binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(1)
+@binding(1)
+var s_diffuse: sampler;
+
+@group(1)
+@binding(2)
+var<uniform> params: ParticleUniform;
+
+struct CameraUniform {
+    view_projection: mat4x4<f32>
+    }
+
+struct ParticleUniform {
+    right: vec3<f32>,
+    size: f32,
+    up: vec3<f32>,
+    delta: f32,
+    gravity: vec3<f32>,
+    seed: u32,
+    count: u32
+    }
+
+struct ParticleInput {
+    @location(0) position: vec3<f32>,
+    @location(1) age: f32,
+    @location(2) lifetime: f32
+    }
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) alpha: f32
+    }
+
+// Two triangles of a quad, drawn without an index buffer
+const CORNERS = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 1.0)
+    );
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, particle: ParticleInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    // Particles which are not emitted yet are moved behind the far plane
+    if particle.age < 0.0 {
+        out.clip_position = vec4<f32>(0.0, 0.0, 2.0, 1.0);
+        return out;
+        }
+
+    var corners = CORNERS;
+    let corner = corners[vertex_index];
+    let offset = (corner - 0.5) * params.size;
+    let position = particle.position + params.right * offset.x + params.up * offset.y;
+
+    out.clip_position = camera.view_projection * vec4<f32>(position, 1.0);
+    out.tex_coords = vec2<f32>(corner.x, 1.0 - corner.y);
+    // Fades out towards the end of the lifetime
+    out.alpha = 1.0 - clamp(particle.age / particle.lifetime, 0.0, 1.0);
+    return out;
+    }
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    return vec4<f32>(color.rgb, color.a * in.alpha);
+    }
\ No newline at end of file
@@ -0,0 +1,50 @@
+@group(0)
+@binding(0)
+var<uniform> camera: CameraUniform;
+
+struct CameraUniform {
+    view_projection: mat4x4<f32>
+    }
+
+struct InstanceInput {
+    @location(5) row0: vec4<f32>,
+    @location(6) row1: vec4<f32>,
+    @location(7) row2: vec4<f32>,
+    @location(8) row3: vec4<f32>
+    }
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>
+    }
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) @interpolate(flat) instance_index: u32
+    }
+
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput, @builtin(instance_index) instance_index: u32) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.row0,
+        instance.row1,
+        instance.row2,
+        instance.row3
+        );
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_projection * model_matrix * vec4<f32>(model.position, 1.0);
+    out.instance_index = instance_index;
+    return out;
+    }
+
+// Zero is left for the background, so the indices are offset by one
+@fragment
+fn fs_id(in: VertexOutput) -> @location(0) u32 {
+    return in.instance_index + 1u;
+    }
+
+@fragment
+fn fs_highlight(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.4, 0.3, 0.0, 0.0);
+    }
\ No newline at end of file
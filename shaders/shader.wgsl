@@ -20,7 +20,8 @@ struct VertexInput {
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) world_position: vec3<f32>
     }
 
 @vertex
@@ -32,9 +33,12 @@ fn vs_main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
         instance.row3
         );
 
+    let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
-    out.clip_position = camera.view_projection * model_matrix * vec4<f32>(model.position, 1.0);
+    out.world_position = world_position.xyz;
+    out.clip_position = camera.view_projection * world_position;
     return out;
     }
 
@@ -46,7 +50,41 @@ var t_diffuse: texture_2d<f32>;
 @binding(1)
 var s_diffuse: sampler;
 
+@group(2)
+@binding(0)
+var<uniform> lighting: LightUniform;
+
+struct Light {
+    position: vec3<f32>,
+    intensity: f32,
+    color: vec3<f32>
+    }
+
+struct LightUniform {
+    lights: array<Light, 8>,
+    count: u32
+    }
+
+// Light reaching the surfaces facing away from every light
+const AMBIENT: f32 = 0.1;
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // Scenes without lights are shown unlit
+    if lighting.count == 0u {
+        return color;
+        }
+
+    // Flat normals from the screen space derivatives, as the vertices carry none
+    let normal = normalize(cross(dpdy(in.world_position), dpdx(in.world_position)));
+
+    var diffuse = vec3<f32>(AMBIENT);
+    for (var i = 0u; i < lighting.count; i += 1u) {
+        let light = lighting.lights[i];
+        let direction = normalize(light.position - in.world_position);
+        diffuse += light.color * light.intensity * max(dot(normal, direction), 0.0);
+        }
+
+    return vec4<f32>(color.rgb * diffuse, color.a);
     }
\ No newline at end of file
@@ -5,44 +5,95 @@ use {
     winit::{
         application::ApplicationHandler,
         event::*,
-        event_loop::ActiveEventLoop,
+        event_loop::{
+            ActiveEventLoop,
+            EventLoopProxy
+            },
         keyboard::PhysicalKey,
         window::{
             Window,
             WindowId
             }
         },
-    std::sync::Arc,
-    crate::state::State
+    std::{
+        path::PathBuf,
+        sync::Arc
+        },
+    crate::{
+        assets::LoadedAsset,
+        input::KeyBindings,
+        scene::SceneDescription,
+        settings::Settings,
+        state::State
+        }
     };
 
+// Commands sent through the EventLoopProxy, mostly by background threads
+pub enum AppEvent {
+    AssetLoaded(LoadedAsset),
+    AssetChanged(PathBuf),
+    LoadScene(PathBuf),
+    SceneLoaded {
+        path: PathBuf,
+        description: SceneDescription
+        },
+    SaveScene(PathBuf),
+    LoadCameraPath(PathBuf),
+    Screenshot(PathBuf),
+    // Slot of the instance buffer under the cursor, None for the background
+    InstancePicked {
+        slot: Option<usize>,
+        // Scene generation the pick was requested in, slots of older scenes are meaningless
+        generation: u64
+        },
+    Shutdown
+    }
+
 pub struct App {
-    state: Option<State>
+    proxy: EventLoopProxy<AppEvent>,
+    settings: Settings,
+    state: Option<State>,
+    scene_path: Option<PathBuf>,
+    key_bindings: Option<KeyBindings>
     }
 
 impl App {
-    pub const fn new() -> Self {
+    pub const fn new(proxy: EventLoopProxy<AppEvent>, settings: Settings, scene_path: Option<PathBuf>, key_bindings: KeyBindings) -> Self {
         Self {
-            state: None
+            proxy,
+            settings,
+            state: None,
+            scene_path,
+            key_bindings: Some(key_bindings)
             }
         }
     }
 
-impl ApplicationHandler<State> for App {
+impl ApplicationHandler<AppEvent> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         let window_attributes = Window::default_attributes();
         let window = Arc::new(
             event_loop.create_window(window_attributes)
                 .expect("Problem occured while resumong the window")
             );
+        // Bindings are handed over to the first created state, and the following ones fall back to the defaults
+        let key_bindings = self.key_bindings.take()
+            .unwrap_or_default();
         self.state = Some(
-            block_on(State::new(window))
+            block_on(State::new(window, self.proxy.clone(), self.settings.clone(), self.scene_path.clone(), key_bindings))
                 .expect("Problem occured while instatiting the state")
             );
         }
 
-    fn user_event(&mut self, _: &ActiveEventLoop, event: State) {
-        self.state = Some(event);
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: AppEvent) {
+        match (event, &mut self.state) {
+            (AppEvent::Shutdown, _) =>
+                event_loop.exit(),
+            (event, Some(state_handle)) =>
+                state_handle.handle_event(event),
+            (_, None) =>
+                warn!("Received an event before the state was created")
+            };
         }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
@@ -56,9 +107,20 @@ impl ApplicationHandler<State> for App {
                 event_loop.exit(),
             WindowEvent::Resized(size) =>
                 state_handle.resize(size),
+            WindowEvent::Focused(is_focused) =>
+                state_handle.set_focused(is_focused),
+            WindowEvent::Occluded(is_occluded) =>
+                state_handle.set_occluded(is_occluded),
+            // The resulting size arrives through WindowEvent::Resized
+            WindowEvent::ScaleFactorChanged { scale_factor, mut inner_size_writer } => {
+                let size = state_handle.set_scale_factor(scale_factor);
+                if let Err(e) = inner_size_writer.request_inner_size(size) {
+                    warn!("Unable to keep the window size {e}");
+                    }
+                },
             WindowEvent::RedrawRequested => {
-                state_handle.update();
-                match state_handle.render() {
+                let interpolation = state_handle.update();
+                match state_handle.render(interpolation) {
                     Err(SurfaceError::Lost | SurfaceError::Outdated) => {
                         let size = state_handle.get_window()
                             .inner_size();
@@ -71,7 +133,19 @@ impl ApplicationHandler<State> for App {
                 },
             WindowEvent::KeyboardInput { event: KeyEvent { physical_key: PhysicalKey::Code(code), state, .. }, .. } =>
                 state_handle.handle_key(event_loop, code, state.is_pressed()),
+            WindowEvent::CursorMoved { position, .. } =>
+                state_handle.set_cursor_position(Some(position)),
+            WindowEvent::CursorLeft { .. } =>
+                state_handle.set_cursor_position(None),
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } =>
+                state_handle.request_pick(),
             _ => ()
             };
         }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(state_handle) = &mut self.state {
+            state_handle.schedule_redraw(event_loop);
+            }
+        }
     }
\ No newline at end of file
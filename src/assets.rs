@@ -0,0 +1,476 @@
+use {
+    anyhow::{
+        Context,
+        Result as DynResult
+        },
+    image::RgbaImage,
+    log::*,
+    rayon::{
+        ThreadPool,
+        ThreadPoolBuilder
+        },
+    wgpu::*,
+    winit::event_loop::EventLoopProxy,
+    std::{
+        collections::HashMap,
+        fmt,
+        marker::PhantomData,
+        path::{
+            Path,
+            PathBuf
+            },
+        sync::{
+            Arc,
+            Weak
+            }
+        },
+    crate::{
+        app::AppEvent,
+        hot_reload::HotReload,
+        mesh::{
+            Mesh,
+            MeshData
+            },
+        source::AssetSource,
+        texture::Texture
+        }
+    };
+
+// Reference-counted index into the storage of Assets, the slot is freed once every handle is dropped
+pub struct Handle<T> {
+    index: Arc<usize>,
+    marker: PhantomData<fn() -> T>
+    }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    Failed
+    }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    Texture,
+    Mesh
+    }
+
+pub struct Asset<T> {
+    path: Option<PathBuf>,
+    state: LoadState,
+    value: T,
+    // Used to tell whether any handle is still alive
+    index: Weak<usize>
+    }
+
+// Texture together with the bind group used by the render pipeline
+#[derive(Clone)]
+pub struct TextureAsset {
+    texture: Texture,
+    bind_group: BindGroup
+    }
+
+// Result of decoding, sent back from the worker threads
+pub enum LoadedAsset {
+    Texture(Handle<TextureAsset>, DynResult<RgbaImage>),
+    Mesh(Handle<Mesh>, DynResult<MeshData>)
+    }
+
+// Entry of the listing of loaded assets
+pub struct AssetInfo {
+    pub kind: AssetKind,
+    pub path: Option<PathBuf>,
+    pub state: LoadState,
+    pub handle_count: usize,
+    pub memory_size: u64
+    }
+
+// Storage of a single asset type, slots of dropped assets are reused
+struct Storage<T> {
+    slots: Vec<Option<Asset<T>>>,
+    by_path: HashMap<PathBuf, Weak<usize>>
+    }
+
+pub struct Assets {
+    device: Device,
+    queue: Queue,
+    texture_layout: BindGroupLayout,
+    placeholder: TextureAsset,
+    source: Arc<AssetSource>,
+    pool: ThreadPool,
+    proxy: EventLoopProxy<AppEvent>,
+    textures: Storage<TextureAsset>,
+    meshes: Storage<Mesh>,
+    hot_reload: Option<HotReload>
+    }
+
+// Derives would require T to implement the traits as well
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            index: self.index.clone(),
+            marker: PhantomData
+            }
+        }
+    }
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+        }
+    }
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Asset<T> {
+    pub fn get_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+        }
+
+    pub const fn get_state(&self) -> LoadState {
+        self.state
+        }
+
+    pub const fn get(&self) -> &T {
+        &self.value
+        }
+    }
+
+impl TextureAsset {
+    pub fn new(device: &Device, layout: &BindGroupLayout, texture: Texture, label: &str) -> Self {
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("{label} Bind Group")),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(texture.get_view())
+                    },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(texture.get_sampler())
+                    }
+                ]
+            });
+
+        Self { texture, bind_group }
+        }
+
+    pub const fn get_texture(&self) -> &Texture {
+        &self.texture
+        }
+
+    pub const fn get_bind_group(&self) -> &BindGroup {
+        &self.bind_group
+        }
+    }
+
+impl fmt::Display for AssetInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = self.path.as_deref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "<inline>".to_owned());
+
+        write!(f, "{:?} {path} ({:?}, {} handles, {:.1} KiB)", self.kind, self.state, self.handle_count, self.memory_size as f64 / 1024.0)
+        }
+    }
+
+impl<T> Storage<T> {
+    fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            by_path: HashMap::new()
+            }
+        }
+
+    // Returns the handle of an asset, which was already requested from the same path
+    fn find(&self, path: &Path) -> Option<Handle<T>> {
+        self.by_path.get(path)
+            .and_then(Weak::upgrade)
+            .map(|index| Handle {
+                index,
+                marker: PhantomData
+                })
+        }
+
+    fn insert(&mut self, path: Option<&Path>, state: LoadState, value: T) -> Handle<T> {
+        let index = self.slots.iter()
+            .position(Option::is_none)
+            .unwrap_or_else(|| {
+                self.slots.push(None);
+                self.slots.len() - 1
+                });
+
+        let handle = Handle {
+            index: Arc::new(index),
+            marker: PhantomData
+            };
+
+        if let Some(path) = path {
+            self.by_path.insert(path.to_owned(), Arc::downgrade(&handle.index));
+            }
+
+        self.slots[index] = Some(Asset {
+            path: path.map(Path::to_owned),
+            state,
+            value,
+            index: Arc::downgrade(&handle.index)
+            });
+
+        handle
+        }
+
+    fn get(&self, handle: &Handle<T>) -> &Asset<T> {
+        // The slot can't be freed while the handle is alive
+        self.slots[*handle.index].as_ref()
+            .expect("Handle points to a freed asset")
+        }
+
+    fn get_mut(&mut self, handle: &Handle<T>) -> &mut Asset<T> {
+        self.slots[*handle.index].as_mut()
+            .expect("Handle points to a freed asset")
+        }
+
+    fn free_unused(&mut self) -> usize {
+        let mut freed = 0;
+        for slot in &mut self.slots {
+            if slot.as_ref().is_some_and(|asset| asset.index.strong_count() == 0) {
+                *slot = None;
+                freed += 1;
+                }
+            }
+
+        self.by_path.retain(|_, index| index.strong_count() > 0);
+
+        freed
+        }
+
+    fn iter(&self) -> impl Iterator<Item = &Asset<T>> {
+        self.slots.iter()
+            .flatten()
+        }
+    }
+
+impl Assets {
+    // Paths of every asset are relative to the source
+    pub fn new(device: &Device, queue: &Queue, texture_layout: &BindGroupLayout, source: AssetSource, proxy: EventLoopProxy<AppEvent>) -> DynResult<Self> {
+        let pool = ThreadPoolBuilder::new()
+            .thread_name(|i| format!("Asset Loader {i}"))
+            .build()?;
+
+        let placeholder = TextureAsset::new(
+            device,
+            texture_layout,
+            Texture::placeholder(device, queue),
+            "Placeholder"
+            );
+
+        // Changes are only picked up during development, and packs can't change at all
+        let hot_reload = match (cfg!(debug_assertions), source.get_directory()) {
+            (true, Some(dir)) => HotReload::new(dir, proxy.clone())
+                .inspect_err(|e| warn!("Hot reload is disabled {e:#}"))
+                .ok(),
+            _ => None
+            };
+
+        Ok(Self {
+            device: device.clone(),
+            queue: queue.clone(),
+            texture_layout: texture_layout.clone(),
+            placeholder,
+            source: Arc::new(source),
+            pool,
+            proxy,
+            textures: Storage::new(),
+            meshes: Storage::new(),
+            hot_reload
+            })
+        }
+
+    // The handle is usable right away, the placeholder gets swapped once decoding finishes
+    pub fn load_texture(&mut self, path: &Path) -> Handle<TextureAsset> {
+        if let Some(handle) = self.textures.find(path) {
+            return handle;
+            }
+
+        let handle = self.textures.insert(Some(path), LoadState::Loading, self.placeholder.clone());
+        self.spawn_texture_job(path, handle.clone());
+
+        handle
+        }
+
+    // Until loaded, the mesh is empty and draws nothing
+    pub fn load_mesh(&mut self, path: &Path) -> Handle<Mesh> {
+        if let Some(handle) = self.meshes.find(path) {
+            return handle;
+            }
+
+        let placeholder = Mesh::new(&self.device, "Placeholder", MeshData::default());
+        let handle = self.meshes.insert(Some(path), LoadState::Loading, placeholder);
+        self.spawn_mesh_job(path, handle.clone());
+
+        handle
+        }
+
+    // Decodes the file again, the old resources stay in use until the new ones are uploaded
+    pub fn reload(&mut self, path: &Path) {
+        if let Some(handle) = self.textures.find(path) {
+            info!("Reloading texture {}", path.display());
+            self.spawn_texture_job(path, handle);
+            }
+        if let Some(handle) = self.meshes.find(path) {
+            info!("Reloading mesh {}", path.display());
+            self.spawn_mesh_job(path, handle);
+            }
+        }
+
+    // For geometry which is already in memory, such meshes are never shared
+    pub fn add_mesh(&mut self, label: &str, data: MeshData) -> Handle<Mesh> {
+        let mesh = Mesh::new(&self.device, label, data);
+        self.meshes.insert(None, LoadState::Loaded, mesh)
+        }
+
+    // Uploads the decoded data, meant to be called after receiving AppEvent::AssetLoaded
+    pub fn finish_load(&mut self, loaded: LoadedAsset) {
+        match loaded {
+            LoadedAsset::Texture(handle, Ok(image)) => {
+                let asset = self.textures.get_mut(&handle);
+                let label = asset_label(asset.path.as_deref());
+                let texture = Texture::from_rgba(&self.device, &self.queue, &image, Some(&label));
+
+                asset.value = TextureAsset::new(&self.device, &self.texture_layout, texture, &label);
+                asset.state = LoadState::Loaded;
+                info!("Loaded texture {label}");
+                },
+            LoadedAsset::Mesh(handle, Ok(data)) => {
+                let asset = self.meshes.get_mut(&handle);
+                let label = asset_label(asset.path.as_deref());
+
+                asset.value = Mesh::new(&self.device, &label, data);
+                asset.state = LoadState::Loaded;
+                info!("Loaded mesh {label}");
+                },
+            // Failed assets keep their placeholders, or the previous version when reloading
+            LoadedAsset::Texture(handle, Err(e)) => {
+                mark_failed(&mut self.textures.get_mut(&handle).state);
+                error!("Unable to load the texture {e:#}");
+                },
+            LoadedAsset::Mesh(handle, Err(e)) => {
+                mark_failed(&mut self.meshes.get_mut(&handle).state);
+                error!("Unable to load the mesh {e:#}");
+                }
+            };
+        }
+
+    pub fn get_texture(&self, handle: &Handle<TextureAsset>) -> &Asset<TextureAsset> {
+        self.textures.get(handle)
+        }
+
+    pub fn get_mesh(&self, handle: &Handle<Mesh>) -> &Asset<Mesh> {
+        self.meshes.get(handle)
+        }
+
+    pub fn is_loading(&self) -> bool {
+        self.textures.iter()
+            .map(Asset::get_state)
+            .chain(self.meshes.iter().map(Asset::get_state))
+            .any(|state| state == LoadState::Loading)
+        }
+
+    // Drops GPU resources of assets without any live handle
+    pub fn free_unused(&mut self) {
+        let freed = self.textures.free_unused() + self.meshes.free_unused();
+        if freed > 0 {
+            debug!("Freed {freed} unused assets");
+            }
+        }
+
+    pub fn list(&self) -> Vec<AssetInfo> {
+        let textures = self.textures.iter()
+            .map(|asset| AssetInfo {
+                kind: AssetKind::Texture,
+                path: asset.path.clone(),
+                state: asset.state,
+                handle_count: asset.index.strong_count(),
+                // Placeholders are shared, so they are not accounted for
+                memory_size: match asset.state {
+                    LoadState::Loaded => asset.value.texture.get_memory_size(),
+                    _ => 0
+                    }
+                });
+
+        let meshes = self.meshes.iter()
+            .map(|asset| AssetInfo {
+                kind: AssetKind::Mesh,
+                path: asset.path.clone(),
+                state: asset.state,
+                handle_count: asset.index.strong_count(),
+                memory_size: asset.value.get_memory_size()
+                });
+
+        textures.chain(meshes)
+            .collect()
+        }
+
+    pub fn get_memory_size(&self) -> u64 {
+        self.list()
+            .iter()
+            .map(|info| info.memory_size)
+            .sum()
+        }
+
+    pub fn get_source(&self) -> &AssetSource {
+        &self.source
+        }
+
+    pub const fn is_hot_reloading(&self) -> bool {
+        self.hot_reload.is_some()
+        }
+
+    // None when the assets come from a pack
+    pub fn get_directory(&self) -> Option<&Path> {
+        self.source.get_directory()
+        }
+
+    fn spawn_texture_job(&self, path: &Path, handle: Handle<TextureAsset>) {
+        let path = path.to_owned();
+        self.spawn(move |source| {
+            let image = source.read(&path)
+                .and_then(|bytes| Texture::decode(&bytes))
+                .with_context(|| format!("Unable to decode texture {}", path.display()));
+
+            LoadedAsset::Texture(handle, image)
+            });
+        }
+
+    fn spawn_mesh_job(&self, path: &Path, handle: Handle<Mesh>) {
+        let path = path.to_owned();
+        self.spawn(move |source| {
+            let data = source.read_to_string(&path)
+                .and_then(|text| MeshData::parse(&text))
+                .with_context(|| format!("Unable to parse mesh {}", path.display()));
+
+            LoadedAsset::Mesh(handle, data)
+            });
+        }
+
+    fn spawn(&self, job: impl FnOnce(&AssetSource) -> LoadedAsset + Send + 'static) {
+        let (source, proxy) = (self.source.clone(), self.proxy.clone());
+        self.pool.spawn(move || {
+            if proxy.send_event(AppEvent::AssetLoaded(job(&source))).is_err() {
+                warn!("Event loop was closed before the asset was loaded");
+                }
+            });
+        }
+    }
+
+const fn mark_failed(state: &mut LoadState) {
+    if let LoadState::Loading = state {
+        *state = LoadState::Failed;
+        }
+    }
+
+fn asset_label(path: Option<&Path>) -> String {
+    path.map(|path| path.display().to_string())
+        .unwrap_or_default()
+    }
\ No newline at end of file
@@ -0,0 +1,28 @@
+use {
+    anyhow::{
+        bail,
+        Result as DynResult
+        },
+    std::{
+        env::args_os,
+        path::PathBuf
+        },
+    wgpu_practice::pack::write_pack
+    };
+
+// Usage: pack <assets directory> <output file>
+fn main() -> DynResult<()> {
+    let args: Vec<_> = args_os()
+        .skip(1)
+        .map(PathBuf::from)
+        .collect();
+
+    let [dir, output] = args.as_slice() else {
+        bail!("Usage: pack <assets directory> <output file>");
+        };
+
+    let count = write_pack(dir, output)?;
+    println!("Packed {count} files from {} into {}", dir.display(), output.display());
+
+    Ok(())
+    }
\ No newline at end of file
@@ -4,8 +4,10 @@ use {
         Zeroable
         },
     cgmath::*,
-    winit::keyboard::*,
-    crate::utils::*
+    crate::{
+        raycast::Ray,
+        utils::*
+        }
     };
 
 const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
@@ -15,6 +17,7 @@ const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
     0.0, 0.0, 0.5, 1.0
     );
 
+#[derive(Clone)]
 pub struct Camera {
     eye: Point3<f32>,
     target: Point3<f32>,
@@ -38,12 +41,88 @@ impl Camera {
             }
         }
 
-    fn build_view_projection_matrix(&self) -> Matrix4<f32> {
+    pub const fn get_eye(&self) -> Point3<f32> {
+        self.eye
+        }
+
+    pub const fn get_target(&self) -> Point3<f32> {
+        self.target
+        }
+
+    pub const fn set_eye(&mut self, eye: Point3<f32>) {
+        self.eye = eye;
+        }
+
+    pub const fn set_target(&mut self, target: Point3<f32>) {
+        self.target = target;
+        }
+
+    pub const fn get_fovy(&self) -> f32 {
+        self.fovy
+        }
+
+    pub const fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+        }
+
+    pub const fn set_fovy(&mut self, fovy: f32) {
+        self.fovy = fovy;
+        }
+
+    pub const fn get_znear(&self) -> f32 {
+        self.znear
+        }
+
+    pub const fn get_zfar(&self) -> f32 {
+        self.zfar
+        }
+
+    // Moving both the eye and the target keeps the pitch unchanged
+    pub fn move_vertically(&mut self, amount: f32, min_height: f32, max_height: f32) {
+        // Clamped only in the direction of travel, so cameras placed outside of the limits never jump, and can still move back
+        let height = match amount > 0.0 {
+            true => (self.eye.y + amount).min(max_height.max(self.eye.y)),
+            false => (self.eye.y + amount).max(min_height.min(self.eye.y))
+            };
+        let offset = height - self.eye.y;
+
+        self.eye.y += offset;
+        self.target.y += offset;
+        }
+
+    // Blends the point of view towards the other camera, the remaining values are taken from it
+    pub fn lerp(&self, other: &Self, amount: f32) -> Self {
+        Self {
+            eye: self.eye + (other.eye - self.eye) * amount,
+            target: self.target + (other.target - self.target) * amount,
+            fovy: self.fovy + (other.fovy - self.fovy) * amount,
+            .. other.clone()
+            }
+        }
+
+    pub fn build_view_projection_matrix(&self) -> Matrix4<f32> {
         let view = Matrix4::look_at_rh(self.eye, self.target, Vector3::unit_y());
         let projection = perspective(Deg(self.fovy), self.aspect, self.znear, self.zfar);
 
         OPENGL_TO_WGPU_MATRIX * projection * view
         }
+
+    // Ray from the near plane through a pixel, the position is in pixels of a target with the given size
+    pub fn screen_ray(&self, position: Vec2<f32>, size: Vec2<f32>) -> Ray {
+        let inverse = self.build_view_projection_matrix()
+            .invert()
+            .unwrap_or_else(Matrix4::identity);
+
+        // Pixel rows go down, while the clip space goes up
+        let x = position[0] / size[0] * 2.0 - 1.0;
+        let y = 1.0 - position[1] / size[1] * 2.0;
+
+        let unproject = |depth| Point3::from_homogeneous(inverse * Vector4::new(x, y, depth, 1.0));
+        let near = unproject(0.0);
+        let far = unproject(1.0);
+
+        Ray::new(near, far - near)
+        }
     }
 
 // For correct representation in shader
@@ -67,84 +146,58 @@ impl CameraUniform {
         }
     }
 
-pub struct CameraController {
-    speed: f32,
-    is_forward_pressed: bool,
-    is_backward_pressed: bool,
-    is_left_pressed: bool,
-    is_right_pressed: bool,
-    is_up_pressed: bool,
-    is_down_pressed: bool
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+        }
     }
 
-impl CameraController {
-    pub const fn new(speed: f32) -> Self {
-        Self {
-            speed,
-            is_forward_pressed: false,
-            is_backward_pressed: false,
-            is_left_pressed: false,
-            is_right_pressed: false,
-            is_up_pressed: false,
-            is_down_pressed: false
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera_at_height(height: f32) -> Camera {
+        Camera::new([0.0, height, 5.0], [0.0, height - 1.0, 0.0], 1.0, 45.0, 0.1, 100.0)
         }
 
-    pub fn handle_key(&mut self, key: KeyCode, is_pressed: bool) -> bool {
-        match key {
-            KeyCode::KeyW | KeyCode::ArrowUp => {
-                self.is_forward_pressed = is_pressed;
-                true
-                },
-            KeyCode::KeyS | KeyCode::ArrowDown => {
-                self.is_backward_pressed = is_pressed;
-                true
-                },
-            KeyCode::KeyA | KeyCode::ArrowLeft => {
-                self.is_left_pressed = is_pressed;
-                true
-                },
-            KeyCode::KeyD | KeyCode::ArrowRight => {
-                self.is_right_pressed = is_pressed;
-                true
-                },
-            KeyCode::Space => {
-                self.is_up_pressed = is_pressed;
-                true
-                },
-            KeyCode::ShiftLeft | KeyCode::ShiftRight => {
-                self.is_down_pressed = is_pressed;
-                true
-                },
-            _ => false
-            }
+    #[test]
+    fn vertical_moves_stop_at_the_limits() {
+        let mut camera = camera_at_height(5.0);
+        camera.move_vertically(10.0, 1.0, 10.0);
+        assert_eq!(camera.get_eye().y, 10.0);
+        // The target keeps its offset, so the pitch stays the same
+        assert_eq!(camera.get_target().y, 9.0);
+
+        camera.move_vertically(-20.0, 1.0, 10.0);
+        assert_eq!(camera.get_eye().y, 1.0);
+        assert_eq!(camera.get_target().y, 0.0);
         }
 
-    pub fn update_camera(&self, camera: &mut Camera) {
-        let forward = camera.target - camera.eye;
-        let forward_norm = forward.normalize();
-        let forward_mag = forward.magnitude();
+    #[test]
+    fn cameras_above_the_limit_only_move_down() {
+        let mut camera = camera_at_height(20.0);
+        camera.move_vertically(1.0, 1.0, 10.0);
+        assert_eq!(camera.get_eye().y, 20.0);
 
-        // Prevent glitches while too close to the centre of the scene
-        if self.is_forward_pressed && self.speed < forward_mag {
-            camera.eye += forward_norm * self.speed;
-            }
-        if self.is_backward_pressed {
-            camera.eye -= forward_norm * self.speed;
-            }
+        camera.move_vertically(-2.0, 1.0, 10.0);
+        assert_eq!(camera.get_eye().y, 18.0);
+        assert_eq!(camera.get_target().y, 17.0);
 
-        let right = forward_norm.cross(Vector3::unit_y());
+        camera.move_vertically(-20.0, 1.0, 10.0);
+        assert_eq!(camera.get_eye().y, 1.0);
+        }
 
-        // Double-check in case front/back is pressed
-        let forward = camera.target - camera.eye;
-        let forward_mag = forward.magnitude();
+    #[test]
+    fn cameras_below_the_limit_only_move_up() {
+        let mut camera = camera_at_height(-5.0);
+        camera.move_vertically(-1.0, 1.0, 10.0);
+        assert_eq!(camera.get_eye().y, -5.0);
 
-        // Rescale the distance between the target, and the eye, so that the eye lies on a cricle around the target
-        if self.is_right_pressed {
-            camera.eye = camera.target - (forward + right * self.speed).normalize() * forward_mag;
-            }
-        if self.is_left_pressed {
-            camera.eye = camera.target - (forward - right * self.speed).normalize() * forward_mag;
-            }
+        camera.move_vertically(2.0, 1.0, 10.0);
+        assert_eq!(camera.get_eye().y, -3.0);
+        assert_eq!(camera.get_target().y, -4.0);
+
+        camera.move_vertically(20.0, 1.0, 10.0);
+        assert_eq!(camera.get_eye().y, 10.0);
         }
     }
\ No newline at end of file
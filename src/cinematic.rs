@@ -0,0 +1,498 @@
+use {
+    anyhow::{
+        bail,
+        Context,
+        Result as DynResult
+        },
+    cgmath::*,
+    ron::ser::PrettyConfig,
+    serde::{
+        Deserialize,
+        Serialize
+        },
+    std::{
+        fs,
+        path::Path,
+        time::Duration
+        },
+    crate::{
+        camera::Camera,
+        utils::*
+        }
+    };
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Keyframe {
+    // In seconds since the start of the path
+    pub time: f32,
+    pub eye: Vec3<f32>,
+    pub target: Vec3<f32>,
+    pub fovy: f32
+    }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Interpolation {
+    Linear,
+    // Passes through every keyframe
+    #[default]
+    CatmullRom,
+    // Uses keyframes as control points, so only the first, and the last one are reached
+    Bezier
+    }
+
+// Applied to the time of the whole path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut
+    }
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub keyframes: Vec<Keyframe>,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(default)]
+    pub easing: Easing
+    }
+
+// Plays a CameraPath on the camera, overriding the active controller
+pub struct Cinematic {
+    path: CameraPath,
+    time: f32,
+    speed: f32,
+    is_playing: bool,
+    // Field of view from before the playback, restored when it stops
+    previous_fovy: Option<f32>
+    }
+
+impl Keyframe {
+    pub fn from_camera(camera: &Camera, time: f32) -> Self {
+        Self {
+            time,
+            eye: camera.get_eye().into(),
+            target: camera.get_target().into(),
+            fovy: camera.get_fovy()
+            }
+        }
+
+    // Vector of (eye, target, fovy) so all of the values can be interpolated at once
+    fn to_values(self) -> [Vector3<f32>; 3] {
+        [self.eye.into(), self.target.into(), Vector3::new(self.fovy, 0.0, 0.0)]
+        }
+    }
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => t * (2.0 - t),
+            Self::EaseInOut => t * t * (3.0 - 2.0 * t)
+            }
+        }
+    }
+
+impl CameraPath {
+    // Keyframes of the file may come in any order, they are sorted by time afterwards
+    pub fn load(path: &Path) -> DynResult<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read camera path {}", path.display()))?;
+
+        let mut camera_path: Self = ron::from_str(&text)
+            .with_context(|| format!("Unable to parse camera path {}", path.display()))?;
+
+        for (index, keyframe) in camera_path.keyframes.iter().enumerate() {
+            if ! keyframe.time.is_finite() || keyframe.time < 0.0 {
+                bail!("Keyframe {index} of camera path {} has an invalid time {}", path.display(), keyframe.time);
+                }
+            }
+
+        camera_path.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(camera_path)
+        }
+
+    pub fn save(&self, path: &Path) -> DynResult<()> {
+        let text = ron::ser::to_string_pretty(self, PrettyConfig::default())?;
+
+        fs::write(path, text)
+            .with_context(|| format!("Unable to write camera path {}", path.display()))
+        }
+
+    // Time of the first keyframe, playback starts there
+    pub fn get_start(&self) -> f32 {
+        self.keyframes.first()
+            .map(|keyframe| keyframe.time)
+            .unwrap_or_default()
+        }
+
+    pub fn get_duration(&self) -> f32 {
+        self.keyframes.last()
+            .map(|keyframe| keyframe.time)
+            .unwrap_or_default()
+        }
+
+    // Keyframes are kept sorted by time
+    pub fn add_keyframe(&mut self, keyframe: Keyframe) {
+        let index = self.keyframes.partition_point(|other| other.time <= keyframe.time);
+        self.keyframes.insert(index, keyframe);
+        }
+
+    // Returns (eye, target, fovy) at the given time
+    pub fn sample(&self, time: f32) -> Option<(Point3<f32>, Point3<f32>, f32)> {
+        let first = self.keyframes.first()?;
+        let duration = self.get_duration() - first.time;
+
+        let time = match duration > 0.0 {
+            true => first.time + self.easing.apply(((time - first.time) / duration).clamp(0.0, 1.0)) * duration,
+            false => first.time
+            };
+
+        let [eye, target, fovy] = match self.interpolation {
+            Interpolation::Linear => self.sample_segment(time, |values, t| values[1] + (values[2] - values[1]) * t),
+            Interpolation::CatmullRom => self.sample_segment(time, catmull_rom),
+            Interpolation::Bezier => match duration > 0.0 {
+                true => self.sample_bezier((time - first.time) / duration),
+                false => first.to_values()
+                }
+            };
+
+        Some((Point3::from_vec(eye), Point3::from_vec(target), fovy.x))
+        }
+
+    // Calls the function with four keyframes around the time, and the progress between the middle two
+    fn sample_segment(&self, time: f32, interpolate: impl Fn([Vector3<f32>; 4], f32) -> Vector3<f32>) -> [Vector3<f32>; 3] {
+        let last = self.keyframes.len() - 1;
+        let index = self.keyframes.partition_point(|keyframe| keyframe.time <= time)
+            .saturating_sub(1)
+            .min(last);
+
+        let (from, to) = (self.keyframes[index], self.keyframes[(index + 1).min(last)]);
+        let t = match to.time > from.time {
+            true => ((time - from.time) / (to.time - from.time)).clamp(0.0, 1.0),
+            false => 0.0
+            };
+
+        // Endpoints are repeated for the missing neighbours
+        let frames = [
+            self.keyframes[index.saturating_sub(1)],
+            from,
+            to,
+            self.keyframes[(index + 2).min(last)]
+            ]
+            .map(Keyframe::to_values);
+
+        [0, 1, 2].map(|i| interpolate(frames.map(|values| values[i]), t))
+        }
+
+    // De Casteljau's algorithm over all of the keyframes
+    fn sample_bezier(&self, t: f32) -> [Vector3<f32>; 3] {
+        let mut points: Vec<_> = self.keyframes.iter()
+            .map(|keyframe| keyframe.to_values())
+            .collect();
+
+        while points.len() > 1 {
+            points = points.windows(2)
+                .map(|pair| [0, 1, 2].map(|i| pair[0][i] + (pair[1][i] - pair[0][i]) * t))
+                .collect();
+            }
+
+        points[0]
+        }
+    }
+
+fn catmull_rom([p0, p1, p2, p3]: [Vector3<f32>; 4], t: f32) -> Vector3<f32> {
+    let (t2, t3) = (t * t, t * t * t);
+
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+    }
+
+impl Cinematic {
+    pub fn new(path: CameraPath) -> Self {
+        Self {
+            path,
+            time: 0.0,
+            speed: 1.0,
+            is_playing: false,
+            previous_fovy: None
+            }
+        }
+
+    pub const fn is_playing(&self) -> bool {
+        self.is_playing
+        }
+
+    pub fn toggle_playback(&mut self, camera: &mut Camera) {
+        match self.is_playing {
+            true => self.stop(camera),
+            false => self.play(camera)
+            };
+        }
+
+    pub fn play(&mut self, camera: &Camera) {
+        if self.path.keyframes.is_empty() {
+            return;
+            }
+
+        // Finished playback starts over
+        let (start, duration) = (self.path.get_start(), self.path.get_duration());
+        let is_finished = match self.speed < 0.0 {
+            true => self.time <= start,
+            false => self.time >= duration
+            };
+        if is_finished {
+            self.restart();
+            }
+
+        self.previous_fovy.get_or_insert(camera.get_fovy());
+        self.is_playing = true;
+        }
+
+    pub fn stop(&mut self, camera: &mut Camera) {
+        self.is_playing = false;
+        if let Some(fovy) = self.previous_fovy.take() {
+            camera.set_fovy(fovy);
+            }
+        }
+
+    // Back to the first keyframe, or to the last one when playing backwards
+    pub fn restart(&mut self) {
+        self.time = match self.speed < 0.0 {
+            true => self.path.get_duration(),
+            false => self.path.get_start()
+            };
+        }
+
+    // Playback speed multiplier, negative values play backwards
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+        }
+
+    pub const fn get_speed(&self) -> f32 {
+        self.speed
+        }
+
+    // New keyframes are placed after the last one
+    pub fn record_keyframe(&mut self, camera: &Camera, interval: f32) -> &Keyframe {
+        let time = match self.path.keyframes.is_empty() {
+            true => 0.0,
+            false => self.path.get_duration() + interval
+            };
+
+        self.path.add_keyframe(Keyframe::from_camera(camera, time));
+        self.path.keyframes.last()
+            .expect("Keyframe was just added")
+        }
+
+    pub fn update(&mut self, camera: &mut Camera, dt: Duration) {
+        if ! self.is_playing {
+            return;
+            }
+
+        // Bounds are applied one by one, as clamp panics for paths edited out of order
+        let start = self.path.get_start();
+        self.time = (self.time + dt.as_secs_f32() * self.speed)
+            .min(self.path.get_duration())
+            .max(start);
+
+        if let Some((eye, target, fovy)) = self.path.sample(self.time) {
+            camera.set_eye(eye);
+            camera.set_target(target);
+            camera.set_fovy(fovy);
+            }
+
+        let is_finished = match self.speed < 0.0 {
+            true => self.time <= start,
+            false => self.time >= self.path.get_duration()
+            };
+        if is_finished {
+            self.stop(camera);
+            }
+        }
+
+    pub const fn get_path(&self) -> &CameraPath {
+        &self.path
+        }
+
+    pub fn set_path(&mut self, path: CameraPath, camera: &mut Camera) {
+        self.stop(camera);
+        self.path = path;
+        self.restart();
+        }
+    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f32, x: f32, fovy: f32) -> Keyframe {
+        Keyframe {
+            time,
+            eye: [x, 1.0, 0.0],
+            target: [x, 0.0, -1.0],
+            fovy
+            }
+        }
+
+    fn path(interpolation: Interpolation, easing: Easing) -> CameraPath {
+        CameraPath {
+            keyframes: vec![keyframe(1.0, 0.0, 40.0), keyframe(2.0, 2.0, 50.0), keyframe(3.0, 4.0, 60.0)],
+            interpolation,
+            easing
+            }
+        }
+
+    fn camera() -> Camera {
+        Camera::new([0.0, 5.0, 5.0], [0.0; 3], 1.0, 45.0, 0.1, 100.0)
+        }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{a} is not {b}");
+        }
+
+    #[test]
+    fn easing_keeps_the_endpoints() {
+        for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+            assert_near(easing.apply(0.0), 0.0);
+            assert_near(easing.apply(1.0), 1.0);
+            }
+
+        assert_near(Easing::Linear.apply(0.25), 0.25);
+        assert_near(Easing::EaseIn.apply(0.5), 0.25);
+        assert_near(Easing::EaseOut.apply(0.5), 0.75);
+        assert_near(Easing::EaseInOut.apply(0.5), 0.5);
+        assert_near(Easing::EaseInOut.apply(0.25), 0.15625);
+        }
+
+    #[test]
+    fn samples_are_clamped_to_the_path() {
+        assert!(CameraPath::default().sample(0.0).is_none());
+
+        let path = path(Interpolation::Linear, Easing::Linear);
+        let (eye, target, fovy) = path.sample(0.0).unwrap();
+        assert_eq!(eye, Point3::new(0.0, 1.0, 0.0));
+        assert_eq!(target, Point3::new(0.0, 0.0, -1.0));
+        assert_near(fovy, 40.0);
+
+        let (eye, _, fovy) = path.sample(10.0).unwrap();
+        assert_near(eye.x, 4.0);
+        assert_near(fovy, 60.0);
+        }
+
+    #[test]
+    fn linear_samples_blend_neighbouring_keyframes() {
+        let path = path(Interpolation::Linear, Easing::Linear);
+        let (eye, _, fovy) = path.sample(1.5).unwrap();
+        assert_near(eye.x, 1.0);
+        assert_near(fovy, 45.0);
+
+        let (eye, _, _) = path.sample(2.75).unwrap();
+        assert_near(eye.x, 3.5);
+        }
+
+    #[test]
+    fn catmull_rom_passes_through_every_keyframe() {
+        let path = path(Interpolation::CatmullRom, Easing::Linear);
+        for (time, x) in [(1.0, 0.0), (2.0, 2.0), (3.0, 4.0)] {
+            assert_near(path.sample(time).unwrap().0.x, x);
+            }
+
+        // The repeated first keyframe makes the curve start slower than the line
+        assert_near(path.sample(1.5).unwrap().0.x, 0.875);
+
+        let curve = catmull_rom([0.0, 0.0, 1.0, 1.0].map(|x| Vector3::new(x, 0.0, 0.0)), 0.5);
+        assert_near(curve.x, 0.5);
+        let curve = catmull_rom([0.0, 1.0, 1.0, 0.0].map(|x| Vector3::new(x, 0.0, 0.0)), 0.5);
+        assert_near(curve.x, 1.125);
+        }
+
+    #[test]
+    fn bezier_reaches_only_the_endpoints() {
+        let mut path = path(Interpolation::Bezier, Easing::Linear);
+        path.keyframes[1].eye[0] = 4.0;
+
+        assert_near(path.sample(1.0).unwrap().0.x, 0.0);
+        assert_near(path.sample(3.0).unwrap().0.x, 4.0);
+        // (1 - t)^2 * 0 + 2 * (1 - t) * t * 4 + t^2 * 4
+        assert_near(path.sample(2.0).unwrap().0.x, 3.0);
+        assert!(path.sample(2.0).unwrap().0.x < 4.0);
+        }
+
+    #[test]
+    fn easing_applies_to_the_whole_path() {
+        let path = path(Interpolation::Linear, Easing::EaseIn);
+        // Half of the time is a quarter of the way
+        assert_near(path.sample(2.0).unwrap().0.x, 1.0);
+        assert_near(path.sample(3.0).unwrap().0.x, 4.0);
+        }
+
+    #[test]
+    fn playback_stops_at_the_end_and_starts_over() {
+        let mut camera = camera();
+        let mut cinematic = Cinematic::new(path(Interpolation::Linear, Easing::Linear));
+
+        cinematic.play(&camera);
+        cinematic.update(&mut camera, Duration::ZERO);
+        assert!(cinematic.is_playing());
+        assert_near(camera.get_eye().x, 0.0);
+        assert_near(camera.get_fovy(), 40.0);
+
+        cinematic.update(&mut camera, Duration::from_secs_f32(0.5));
+        assert_near(camera.get_eye().x, 1.0);
+
+        cinematic.update(&mut camera, Duration::from_secs(5));
+        assert!(! cinematic.is_playing());
+        assert_near(camera.get_eye().x, 4.0);
+        // Field of view from before the playback is restored
+        assert_near(camera.get_fovy(), 45.0);
+
+        cinematic.play(&camera);
+        cinematic.update(&mut camera, Duration::ZERO);
+        assert!(cinematic.is_playing());
+        assert_near(camera.get_eye().x, 0.0);
+        }
+
+    #[test]
+    fn backwards_playback_starts_at_the_end() {
+        let mut camera = camera();
+        let mut cinematic = Cinematic::new(path(Interpolation::Linear, Easing::Linear));
+        cinematic.set_speed(-2.0);
+
+        cinematic.play(&camera);
+        cinematic.update(&mut camera, Duration::from_secs_f32(0.25));
+        assert_near(camera.get_eye().x, 3.0);
+
+        cinematic.update(&mut camera, Duration::from_secs(5));
+        assert!(! cinematic.is_playing());
+        assert_near(camera.get_eye().x, 0.0);
+        }
+
+    #[test]
+    fn restart_goes_back_to_the_first_keyframe() {
+        let mut camera = camera();
+        let mut cinematic = Cinematic::new(path(Interpolation::Linear, Easing::Linear));
+
+        cinematic.play(&camera);
+        cinematic.update(&mut camera, Duration::from_secs_f32(1.5));
+        cinematic.restart();
+        cinematic.update(&mut camera, Duration::ZERO);
+        assert_near(camera.get_eye().x, 0.0);
+
+        // Or to the last one, when playing backwards
+        cinematic.set_speed(-1.0);
+        cinematic.restart();
+        cinematic.update(&mut camera, Duration::ZERO);
+        assert_near(camera.get_eye().x, 4.0);
+
+        cinematic.set_path(path(Interpolation::Linear, Easing::Linear), &mut camera);
+        assert!(! cinematic.is_playing());
+        cinematic.play(&camera);
+        cinematic.update(&mut camera, Duration::ZERO);
+        assert_near(camera.get_eye().x, 4.0);
+        }
+    }
@@ -0,0 +1,361 @@
+use {
+    cgmath::*,
+    winit::keyboard::KeyCode,
+    std::{
+        any::Any,
+        f32::consts::{
+            FRAC_PI_2,
+            TAU
+            },
+        time::Duration
+        },
+    crate::{
+        camera::Camera,
+        utils::*
+        }
+    };
+
+// Looking straight up, or down would make the view matrix degenerate
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+// Any allows finding controllers by their type, like the FixedController following the scene camera
+pub trait CameraController: Any {
+    fn get_name(&self) -> &str;
+
+    // Returns whether the key was used by the controller
+    fn handle_key(&mut self, key: KeyCode, is_pressed: bool) -> bool;
+
+    fn update_camera(&mut self, camera: &mut Camera, dt: Duration);
+
+    // Called when the controller stops being the active one, so no key stays held
+    fn reset(&mut self) {}
+
+    // Whether the camera keeps moving without any new input, so frames have to be drawn
+    fn is_animating(&self) -> bool {
+        false
+        }
+    }
+
+#[derive(Default)]
+struct MovementKeys {
+    is_forward_pressed: bool,
+    is_backward_pressed: bool,
+    is_left_pressed: bool,
+    is_right_pressed: bool,
+    is_up_pressed: bool,
+    is_down_pressed: bool
+    }
+
+impl MovementKeys {
+    const fn handle_key(&mut self, key: KeyCode, is_pressed: bool) -> bool {
+        let pressed = match key {
+            KeyCode::KeyW => &mut self.is_forward_pressed,
+            KeyCode::KeyS => &mut self.is_backward_pressed,
+            KeyCode::KeyA => &mut self.is_left_pressed,
+            KeyCode::KeyD => &mut self.is_right_pressed,
+            KeyCode::Space => &mut self.is_up_pressed,
+            KeyCode::ShiftLeft | KeyCode::ShiftRight => &mut self.is_down_pressed,
+            _ => return false
+            };
+
+        *pressed = is_pressed;
+        true
+        }
+
+    // Returns -1, 0, or 1 depending on which of the keys is held
+    const fn axis(positive: bool, negative: bool) -> f32 {
+        match (positive, negative) {
+            (true, false) => 1.0,
+            (false, true) => -1.0,
+            _ => 0.0
+            }
+        }
+
+    const fn get_vertical(&self) -> f32 {
+        Self::axis(self.is_up_pressed, self.is_down_pressed)
+        }
+
+    const fn is_any_pressed(&self) -> bool {
+        self.is_forward_pressed || self.is_backward_pressed
+            || self.is_left_pressed || self.is_right_pressed
+            || self.is_up_pressed || self.is_down_pressed
+        }
+    }
+
+// Circles around the target, speed is in units per second
+pub struct OrbitController {
+    speed: f32,
+    min_height: f32,
+    max_height: f32,
+    keys: MovementKeys
+    }
+
+// Moves freely, with the arrow keys used to look around
+pub struct FreeFlyController {
+    speed: f32,
+    // In radians per second
+    turn_speed: f32,
+    min_height: f32,
+    max_height: f32,
+    keys: MovementKeys,
+    is_look_left_pressed: bool,
+    is_look_right_pressed: bool,
+    is_look_up_pressed: bool,
+    is_look_down_pressed: bool
+    }
+
+// Keeps the camera at a single point of view
+pub struct FixedController {
+    eye: Point3<f32>,
+    target: Point3<f32>
+    }
+
+// Follows a looping path of (eye, target) waypoints
+pub struct ScriptedController {
+    waypoints: Vec<(Point3<f32>, Point3<f32>)>,
+    segment_duration: Duration,
+    time: Duration
+    }
+
+impl OrbitController {
+    // Height limits apply to the eye
+    pub fn new(speed: f32, min_height: f32, max_height: f32) -> Self {
+        Self {
+            speed,
+            min_height,
+            max_height,
+            keys: MovementKeys::default()
+            }
+        }
+    }
+
+impl CameraController for OrbitController {
+    fn get_name(&self) -> &str {
+        "Orbit"
+        }
+
+    fn handle_key(&mut self, key: KeyCode, is_pressed: bool) -> bool {
+        // Arrows work the same as WASD
+        let key = match key {
+            KeyCode::ArrowUp => KeyCode::KeyW,
+            KeyCode::ArrowDown => KeyCode::KeyS,
+            KeyCode::ArrowLeft => KeyCode::KeyA,
+            KeyCode::ArrowRight => KeyCode::KeyD,
+            key => key
+            };
+
+        self.keys.handle_key(key, is_pressed)
+        }
+
+    fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
+        let step = self.speed * dt.as_secs_f32();
+        let (eye, target) = (camera.get_eye(), camera.get_target());
+
+        let forward = target - eye;
+        let forward_norm = forward.normalize();
+        let forward_mag = forward.magnitude();
+
+        let mut eye = eye;
+
+        // Prevent glitches while too close to the centre of the scene
+        if self.keys.is_forward_pressed && step < forward_mag {
+            eye += forward_norm * step;
+            }
+        if self.keys.is_backward_pressed {
+            eye -= forward_norm * step;
+            }
+
+        let right = forward_norm.cross(Vector3::unit_y());
+
+        // Double-check in case front/back is pressed
+        let forward = target - eye;
+        let forward_mag = forward.magnitude();
+
+        // Rescale the distance between the target, and the eye, so that the eye lies on a cricle around the target
+        if self.keys.is_right_pressed {
+            eye = target - (forward + right * step).normalize() * forward_mag;
+            }
+        if self.keys.is_left_pressed {
+            eye = target - (forward - right * step).normalize() * forward_mag;
+            }
+
+        camera.set_eye(eye);
+        camera.move_vertically(self.keys.get_vertical() * step, self.min_height, self.max_height);
+        }
+
+    fn reset(&mut self) {
+        self.keys = MovementKeys::default();
+        }
+
+    fn is_animating(&self) -> bool {
+        self.keys.is_any_pressed()
+        }
+    }
+
+impl FreeFlyController {
+    pub fn new(speed: f32, turn_speed: f32, min_height: f32, max_height: f32) -> Self {
+        Self {
+            speed,
+            turn_speed,
+            min_height,
+            max_height,
+            keys: MovementKeys::default(),
+            is_look_left_pressed: false,
+            is_look_right_pressed: false,
+            is_look_up_pressed: false,
+            is_look_down_pressed: false
+            }
+        }
+    }
+
+impl CameraController for FreeFlyController {
+    fn get_name(&self) -> &str {
+        "Free-fly"
+        }
+
+    fn handle_key(&mut self, key: KeyCode, is_pressed: bool) -> bool {
+        let pressed = match key {
+            KeyCode::ArrowLeft => &mut self.is_look_left_pressed,
+            KeyCode::ArrowRight => &mut self.is_look_right_pressed,
+            KeyCode::ArrowUp => &mut self.is_look_up_pressed,
+            KeyCode::ArrowDown => &mut self.is_look_down_pressed,
+            key => return self.keys.handle_key(key, is_pressed)
+            };
+
+        *pressed = is_pressed;
+        true
+        }
+
+    fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
+        let dt = dt.as_secs_f32();
+        let forward = (camera.get_target() - camera.get_eye()).normalize();
+
+        let yaw = forward.z.atan2(forward.x)
+            + MovementKeys::axis(self.is_look_right_pressed, self.is_look_left_pressed) * self.turn_speed * dt;
+        let pitch = (forward.y.asin()
+            + MovementKeys::axis(self.is_look_up_pressed, self.is_look_down_pressed) * self.turn_speed * dt)
+            .clamp(- MAX_PITCH, MAX_PITCH);
+
+        let forward = Vector3::new(yaw.cos() * pitch.cos(), pitch.sin(), yaw.sin() * pitch.cos());
+        let right = forward.cross(Vector3::unit_y())
+            .normalize();
+
+        // Movement stays horizontal, so that looking down doesn't sink the camera
+        let flat_forward = Vector3::new(forward.x, 0.0, forward.z)
+            .normalize();
+
+        let movement = flat_forward * MovementKeys::axis(self.keys.is_forward_pressed, self.keys.is_backward_pressed)
+            + right * MovementKeys::axis(self.keys.is_right_pressed, self.keys.is_left_pressed);
+
+        let mut eye = camera.get_eye();
+        if ! movement.is_zero() {
+            eye += movement.normalize() * self.speed * dt;
+            }
+
+        camera.set_eye(eye);
+        camera.set_target(eye + forward);
+        camera.move_vertically(self.keys.get_vertical() * self.speed * dt, self.min_height, self.max_height);
+        }
+
+    fn reset(&mut self) {
+        self.keys = MovementKeys::default();
+        self.is_look_left_pressed = false;
+        self.is_look_right_pressed = false;
+        self.is_look_up_pressed = false;
+        self.is_look_down_pressed = false;
+        }
+
+    fn is_animating(&self) -> bool {
+        self.keys.is_any_pressed()
+            || self.is_look_left_pressed || self.is_look_right_pressed
+            || self.is_look_up_pressed || self.is_look_down_pressed
+        }
+    }
+
+impl FixedController {
+    pub const fn new(eye: Vec3<f32>, target: Vec3<f32>) -> Self {
+        Self {
+            eye: array_to_point3(eye),
+            target: array_to_point3(target)
+            }
+        }
+
+    pub const fn set_view(&mut self, eye: Vec3<f32>, target: Vec3<f32>) {
+        self.eye = array_to_point3(eye);
+        self.target = array_to_point3(target);
+        }
+    }
+
+impl CameraController for FixedController {
+    fn get_name(&self) -> &str {
+        "Fixed"
+        }
+
+    fn handle_key(&mut self, _: KeyCode, _: bool) -> bool {
+        false
+        }
+
+    fn update_camera(&mut self, camera: &mut Camera, _: Duration) {
+        camera.set_eye(self.eye);
+        camera.set_target(self.target);
+        }
+    }
+
+impl ScriptedController {
+    pub fn new(waypoints: Vec<(Vec3<f32>, Vec3<f32>)>, segment_duration: Duration) -> Self {
+        Self {
+            waypoints: waypoints.into_iter()
+                .map(|(eye, target)| (array_to_point3(eye), array_to_point3(target)))
+                .collect(),
+            segment_duration,
+            time: Duration::ZERO
+            }
+        }
+
+    // Eye placed on a circle around the target
+    pub fn circle(target: Vec3<f32>, radius: f32, height: f32, points: usize, segment_duration: Duration) -> Self {
+        let [x, y, z] = target;
+        let waypoints = (0 .. points)
+            .map(|i| {
+                let angle = i as f32 / points as f32 * TAU;
+                ([x + radius * angle.cos(), y + height, z + radius * angle.sin()], target)
+                })
+            .collect();
+
+        Self::new(waypoints, segment_duration)
+        }
+    }
+
+impl CameraController for ScriptedController {
+    fn get_name(&self) -> &str {
+        "Scripted path"
+        }
+
+    fn handle_key(&mut self, _: KeyCode, _: bool) -> bool {
+        false
+        }
+
+    fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
+        let count = self.waypoints.len();
+        if count == 0 || self.segment_duration.is_zero() {
+            return;
+            }
+
+        // The path loops, so the last waypoint leads back into the first one
+        let loop_duration = self.segment_duration * count as u32;
+        self.time = Duration::from_secs_f32((self.time + dt).as_secs_f32() % loop_duration.as_secs_f32());
+
+        let progress = self.time.div_duration_f32(self.segment_duration);
+        let (index, amount) = (progress as usize % count, progress.fract());
+
+        let (eye_from, target_from) = self.waypoints[index];
+        let (eye_to, target_to) = self.waypoints[(index + 1) % count];
+
+        camera.set_eye(eye_from + (eye_to - eye_from) * amount);
+        camera.set_target(target_from + (target_to - target_from) * amount);
+        }
+
+    fn is_animating(&self) -> bool {
+        ! self.waypoints.is_empty()
+        }
+    }
\ No newline at end of file
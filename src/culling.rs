@@ -0,0 +1,310 @@
+use {
+    bytemuck::{
+        cast_slice,
+        Pod,
+        Zeroable
+        },
+    cgmath::*,
+    wgpu::{
+        *,
+        util::*
+        },
+    std::mem::size_of,
+    crate::{
+        assets::Assets,
+        camera::Camera,
+        instance::{
+            Instance,
+            InstanceRaw
+            },
+        profiler::GpuProfiler,
+        scene::Scene,
+        utils::*
+        }
+    };
+
+// Instances tested by a single invocation group of the compute shader
+const WORKGROUP_SIZE: u32 = 64;
+const DRAW_ARGS_SIZE: BufferAddress = size_of::<DrawIndexedIndirectArgs>() as BufferAddress;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+struct FrustumUniform {
+    planes: [Vec4<f32>; 6],
+    count: u32,
+    _padding: [u32; 3]
+    }
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+struct BatchBounds {
+    center: Vec3<f32>,
+    radius: f32,
+    offset: u32,
+    _padding: [u32; 3]
+    }
+
+// Buffers of a single scene, the instances are uploaded once, the batches every frame
+struct CullingBuffers {
+    num_instances: u32,
+    batch_buffer: Buffer,
+    culled_buffer: Buffer,
+    indirect_buffer: Buffer,
+    bind_group: BindGroup
+    }
+
+// Frustum culls the instances on the GPU, and writes one indirect draw per batch of the scene
+pub struct GpuCulling {
+    device: Device,
+    layout: BindGroupLayout,
+    pipeline: ComputePipeline,
+    uniform_buffer: Buffer,
+    // Empty scenes have no buffers, as empty buffers can't be bound
+    buffers: Option<CullingBuffers>
+    }
+
+impl GpuCulling {
+    // Needs compute shaders, and DownlevelFlags::INDIRECT_EXECUTION
+    pub fn new(device: &Device, scene: &Scene) -> Self {
+        let storage_entry = |binding, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None
+                },
+            count: None
+            };
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Culling Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                        },
+                    count: None
+                    },
+                storage_entry(1, true),
+                storage_entry(2, true),
+                storage_entry(3, true),
+                storage_entry(4, false),
+                storage_entry(5, false)
+                ]
+            });
+
+        let shader = device.create_shader_module(include_wgsl!("../shaders/culling.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Culling Pipeline Layout"),
+            bind_group_layouts: &[
+                &layout
+                ],
+            push_constant_ranges: &[]
+            });
+
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Culling Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("cs_main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None
+            });
+
+        let uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Culling Uniform Buffer"),
+            size: size_of::<FrustumUniform>() as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false
+            });
+
+        let mut culling = Self {
+            device: device.clone(),
+            layout,
+            pipeline,
+            uniform_buffer,
+            buffers: None
+            };
+        culling.set_scene(scene);
+        culling
+        }
+
+    // Has to be called whenever the scene is replaced
+    pub fn set_scene(&mut self, scene: &Scene) {
+        self.buffers = self.create_buffers(scene);
+        }
+
+    fn create_buffers(&self, scene: &Scene) -> Option<CullingBuffers> {
+        let instances = scene.get_instances();
+        if instances.is_empty() {
+            return None;
+            }
+
+        let instances_data: Vec<_> = instances.iter()
+            .map(Instance::to_raw)
+            .collect();
+
+        let instance_batches: Vec<u32> = scene.get_batches()
+            .enumerate()
+            .flat_map(|(batch, (_, range))| range.map(move |_| batch as u32))
+            .collect();
+        let num_batches = scene.get_batches().count();
+
+        let instance_buffer = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Culling Instance Buffer"),
+            contents: cast_slice(&instances_data),
+            usage: BufferUsages::STORAGE
+            });
+
+        let instance_batch_buffer = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Culling Instance Batch Buffer"),
+            contents: cast_slice(&instance_batches),
+            usage: BufferUsages::STORAGE
+            });
+
+        let batch_buffer = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Culling Batch Buffer"),
+            size: (num_batches * size_of::<BatchBounds>()) as BufferAddress,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false
+            });
+
+        let culled_buffer = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Culled Instance Buffer"),
+            size: (instances_data.len() * size_of::<InstanceRaw>()) as BufferAddress,
+            usage: BufferUsages::STORAGE | BufferUsages::VERTEX,
+            mapped_at_creation: false
+            });
+
+        let indirect_buffer = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Indirect Draw Buffer"),
+            size: num_batches as BufferAddress * DRAW_ARGS_SIZE,
+            usage: BufferUsages::STORAGE | BufferUsages::INDIRECT | BufferUsages::COPY_DST,
+            mapped_at_creation: false
+            });
+
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Culling Bind Group"),
+            layout: &self.layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding()
+                    },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: instance_buffer.as_entire_binding()
+                    },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: instance_batch_buffer.as_entire_binding()
+                    },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: batch_buffer.as_entire_binding()
+                    },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: culled_buffer.as_entire_binding()
+                    },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: indirect_buffer.as_entire_binding()
+                    }
+                ]
+            });
+
+        Some(CullingBuffers {
+            num_instances: instances_data.len() as u32,
+            batch_buffer,
+            culled_buffer,
+            indirect_buffer,
+            bind_group
+            })
+        }
+
+    // Records the culling pass, the draws are reset every frame, as meshes may finish loading in the meantime
+    pub fn prepare(&self, queue: &Queue, encoder: &mut CommandEncoder, scene: &Scene, assets: &Assets, camera: &Camera, profiler: Option<&mut GpuProfiler>) {
+        let Some(buffers) = &self.buffers else {
+            return;
+            };
+
+        let (bounds, draws): (Vec<_>, Vec<_>) = scene.get_batches()
+            .map(|(mesh, range)| {
+                let mesh = assets.get_mesh(mesh.get_handle()).get();
+                let (min, max) = mesh.get_bounds();
+
+                // Placeholders have no vertices, and draw nothing anyway
+                let (center, radius) = match mesh.get_num_indices() {
+                    0 => (Point3::origin(), 0.0),
+                    _ => (min.midpoint(max), min.distance(max) * 0.5)
+                    };
+
+                let bounds = BatchBounds {
+                    center: center.into(),
+                    radius,
+                    offset: range.start,
+                    .. Default::default()
+                    };
+
+                // Every batch reads its own range of the culled buffer, so the first instance stays at zero
+                let draw = DrawIndexedIndirectArgs {
+                    index_count: mesh.get_num_indices(),
+                    instance_count: 0,
+                    first_index: 0,
+                    base_vertex: 0,
+                    first_instance: 0
+                    };
+
+                (bounds, draw)
+                })
+            .unzip();
+
+        let uniform = FrustumUniform {
+            planes: extract_frustum_planes(camera.build_view_projection_matrix()),
+            count: buffers.num_instances,
+            .. Default::default()
+            };
+
+        queue.write_buffer(&self.uniform_buffer, 0, cast_slice(&[uniform]));
+        queue.write_buffer(&buffers.batch_buffer, 0, cast_slice(&bounds));
+        queue.write_buffer(&buffers.indirect_buffer, 0, cast_slice(&draws));
+
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Culling Pass"),
+            timestamp_writes: profiler.and_then(|profiler| profiler.compute_pass_timestamps("Culling"))
+            });
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &buffers.bind_group, &[]);
+        compute_pass.dispatch_workgroups(buffers.num_instances.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+
+    // Pair of the culled instances, and the draws, None for empty scenes
+    pub fn get_buffers(&self) -> Option<(&Buffer, &Buffer)> {
+        self.buffers.as_ref()
+            .map(|buffers| (&buffers.culled_buffer, &buffers.indirect_buffer))
+        }
+    }
+
+// Offset of a batch's draw inside of the indirect buffer
+pub const fn get_draw_offset(batch: usize) -> BufferAddress {
+    batch as BufferAddress * DRAW_ARGS_SIZE
+    }
+
+// Left, right, bottom, top, near, and far planes, with the normals pointing inside
+fn extract_frustum_planes(view_projection: Matrix4<f32>) -> [Vec4<f32>; 6] {
+    let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_projection.row(i));
+
+    // Depth of the clip space goes from 0 to 1, so the near plane is the z row alone
+    [w + x, w - x, w + y, w - y, z, w - z].map(|plane| {
+        let length = plane.truncate().magnitude();
+        (plane / length).into()
+        })
+    }
\ No newline at end of file
@@ -0,0 +1,281 @@
+use {
+    bytemuck::{
+        cast_slice,
+        Pod,
+        Zeroable
+        },
+    cgmath::*,
+    wgpu::*,
+    std::{
+        f32::consts::TAU,
+        mem::size_of
+        },
+    crate::{
+        camera::Camera,
+        texture::Texture,
+        utils::*
+        }
+    };
+
+// Line segments used for every circle of a sphere
+const CIRCLE_SEGMENTS: usize = 24;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct LineVertex {
+    position: Vec3<f32>,
+    color: Vec3<f32>
+    }
+
+impl VertexInfo for LineVertex {
+    const DESC: VertexBufferLayout<'static> = VertexBufferLayout {
+        array_stride: size_of::<Self>() as BufferAddress,
+        step_mode: VertexStepMode::Vertex,
+        attributes: &vertex_attr_array![
+            0 => Float32x3,
+            1 => Float32x3
+            ]
+        };
+    }
+
+// Lines of a single depth mode, rebuilt every frame
+struct LineBatch {
+    vertices: Vec<LineVertex>,
+    buffer: Buffer,
+    num_vertices: u32,
+    pipeline: RenderPipeline
+    }
+
+// Immediate-mode debug shapes, which are gone after being drawn once
+pub struct DebugDraw {
+    depth_tested: LineBatch,
+    on_top: LineBatch,
+    // Which of the batches receives new shapes
+    is_on_top: bool
+    }
+
+impl LineBatch {
+    fn new(device: &Device, layout: &PipelineLayout, shader: &ShaderModule, format: TextureFormat, is_on_top: bool) -> Self {
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some(match is_on_top {
+                true => "Debug Lines On Top Pipeline",
+                false => "Debug Lines Pipeline"
+                }),
+            layout: Some(layout),
+            vertex: VertexState {
+                module: shader,
+                entry_point: Some("vs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[
+                    LineVertex::DESC
+                    ]
+                },
+            fragment: Some(FragmentState {
+                module: shader,
+                entry_point: Some("fs_main"),
+                targets: &[
+                    Some(ColorTargetState {
+                        format,
+                        blend: Some(BlendState::REPLACE),
+                        write_mask: ColorWrites::ALL
+                        })
+                    ],
+                compilation_options: PipelineCompilationOptions::default()
+                }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::LineList,
+                .. Default::default()
+                },
+            // Lines never write depth, so they don't hide each other
+            depth_stencil: Some(DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: match is_on_top {
+                    true => CompareFunction::Always,
+                    false => CompareFunction::LessEqual
+                    },
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default()
+                }),
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None
+            });
+
+        Self {
+            vertices: Vec::new(),
+            buffer: create_vertex_buffer(device, 4096),
+            num_vertices: 0,
+            pipeline
+            }
+        }
+
+    fn prepare(&mut self, device: &Device, queue: &Queue) {
+        let size = (self.vertices.len() * size_of::<LineVertex>()) as BufferAddress;
+        if size > self.buffer.size() {
+            self.buffer = create_vertex_buffer(device, size.next_power_of_two());
+            }
+        if size > 0 {
+            queue.write_buffer(&self.buffer, 0, cast_slice(&self.vertices));
+            }
+
+        self.num_vertices = self.vertices.len() as u32;
+        self.vertices.clear();
+        }
+
+    fn draw(&self, render_pass: &mut RenderPass) {
+        if self.num_vertices == 0 {
+            return;
+            }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, self.buffer.slice(..));
+        render_pass.draw(0 .. self.num_vertices, 0 .. 1);
+        }
+    }
+
+impl DebugDraw {
+    // Lines are drawn with the camera bind group at index 0
+    pub fn new(device: &Device, format: TextureFormat, camera_layout: &BindGroupLayout) -> Self {
+        let shader = device.create_shader_module(include_wgsl!("../shaders/lines.wgsl"));
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Debug Lines Pipeline Layout"),
+            bind_group_layouts: &[
+                camera_layout
+                ],
+            push_constant_ranges: &[]
+            });
+
+        Self {
+            depth_tested: LineBatch::new(device, &layout, &shader, format, false),
+            on_top: LineBatch::new(device, &layout, &shader, format, true),
+            is_on_top: false
+            }
+        }
+
+    // Shapes added afterwards are either hidden behind the scene, or drawn over it
+    pub const fn set_on_top(&mut self, is_on_top: bool) -> &mut Self {
+        self.is_on_top = is_on_top;
+        self
+        }
+
+    pub fn line(&mut self, from: Point3<f32>, to: Point3<f32>, color: Vec3<f32>) -> &mut Self {
+        let batch = match self.is_on_top {
+            true => &mut self.on_top,
+            false => &mut self.depth_tested
+            };
+
+        batch.vertices.push(LineVertex { position: from.into(), color });
+        batch.vertices.push(LineVertex { position: to.into(), color });
+        self
+        }
+
+    // Axis-aligned box between the two corners
+    pub fn aabb(&mut self, min: Point3<f32>, max: Point3<f32>, color: Vec3<f32>) -> &mut Self {
+        let corner = |i: usize| Point3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z }
+            );
+        self.box_edges(corner, color)
+        }
+
+    // Three circles, one around each of the axes
+    pub fn sphere(&mut self, center: Point3<f32>, radius: f32, color: Vec3<f32>) -> &mut Self {
+        for axis in 0 .. 3 {
+            let point = |i: usize| {
+                let angle = i as f32 / CIRCLE_SEGMENTS as f32 * TAU;
+                let (sin, cos) = (angle.sin() * radius, angle.cos() * radius);
+                center + match axis {
+                    0 => Vector3::new(0.0, cos, sin),
+                    1 => Vector3::new(cos, 0.0, sin),
+                    _ => Vector3::new(cos, sin, 0.0)
+                    }
+                };
+
+            for i in 0 .. CIRCLE_SEGMENTS {
+                self.line(point(i), point(i + 1), color);
+                }
+            }
+        self
+        }
+
+    // Red, green, and blue lines along the rotated x, y, and z axes
+    pub fn axes(&mut self, position: Point3<f32>, rotation: Quaternion<f32>, size: f32) -> &mut Self {
+        self.line(position, position + rotation.rotate_vector(Vector3::unit_x()) * size, [1.0, 0.0, 0.0]);
+        self.line(position, position + rotation.rotate_vector(Vector3::unit_y()) * size, [0.0, 1.0, 0.0]);
+        self.line(position, position + rotation.rotate_vector(Vector3::unit_z()) * size, [0.0, 0.0, 1.0])
+        }
+
+    // Square grid on the XZ plane, with the given number of cells along each side
+    pub fn grid(&mut self, center: Point3<f32>, size: f32, cells: u32, color: Vec3<f32>) -> &mut Self {
+        let half = size * 0.5;
+        let cells = cells.max(1);
+
+        for i in 0 ..= cells {
+            let offset = i as f32 / cells as f32 * size - half;
+            self.line(center + Vector3::new(offset, 0.0, - half), center + Vector3::new(offset, 0.0, half), color);
+            self.line(center + Vector3::new(- half, 0.0, offset), center + Vector3::new(half, 0.0, offset), color);
+            }
+        self
+        }
+
+    // Volume visible through the camera, from the near to the far plane
+    pub fn frustum(&mut self, camera: &Camera, color: Vec3<f32>) -> &mut Self {
+        let inverse = camera.build_view_projection_matrix()
+            .invert()
+            .unwrap_or_else(Matrix4::identity);
+
+        // Depth of the clip space goes from 0 to 1
+        let corner = |i: usize| {
+            let clip = inverse * Vector4::new(
+                if i & 1 == 0 { - 1.0 } else { 1.0 },
+                if i & 2 == 0 { - 1.0 } else { 1.0 },
+                if i & 4 == 0 { 0.0 } else { 1.0 },
+                1.0
+                );
+            Point3::from_homogeneous(clip)
+            };
+        self.box_edges(corner, color)
+        }
+
+    // Connects the 8 corners, indexed by their bits, into the 12 edges of a box
+    fn box_edges(&mut self, corner: impl Fn(usize) -> Point3<f32>, color: Vec3<f32>) -> &mut Self {
+        for i in 0 .. 8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                    }
+                }
+            }
+        self
+        }
+
+    // Uploads the shapes added since the last call, and clears them
+    pub fn prepare(&mut self, device: &Device, queue: &Queue) {
+        self.depth_tested.prepare(device, queue);
+        self.on_top.prepare(device, queue);
+        }
+
+    // Drops the queued shapes without drawing them, for frames which are skipped
+    pub fn clear(&mut self) {
+        self.depth_tested.vertices.clear();
+        self.on_top.vertices.clear();
+        }
+
+    // The camera bind group has to be set already
+    pub fn draw(&self, render_pass: &mut RenderPass) {
+        self.depth_tested.draw(render_pass);
+        self.on_top.draw(render_pass);
+        }
+    }
+
+fn create_vertex_buffer(device: &Device, size: BufferAddress) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Debug Lines Vertex Buffer"),
+        size,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        mapped_at_creation: false
+        })
+    }
\ No newline at end of file
@@ -0,0 +1,165 @@
+use {
+    log::*,
+    wgpu::*,
+    crate::{
+        assets::Assets,
+        instance::InstanceRaw,
+        scene::Scene,
+        texture::Texture,
+        utils::VertexInfo,
+        vertex::{
+            BarycentricVertex,
+            Vertex
+            }
+        }
+    };
+
+// What the main pass shows instead of the textured scene
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebugView {
+    #[default]
+    Shaded,
+    // Edges drawn over the shaded scene
+    Wireframe,
+    Normals,
+    TexCoords,
+    Depth,
+    InstanceId
+    }
+
+// Pipelines of the debug views, drawn with the camera bind group at index 0
+pub struct DebugViewRenderer {
+    device: Device,
+    normals: RenderPipeline,
+    tex_coords: RenderPipeline,
+    depth: RenderPipeline,
+    instance_id: RenderPipeline,
+    wireframe: RenderPipeline,
+    // Edges come from the rasterizer with Features::POLYGON_MODE_LINE, and from a shader otherwise
+    is_line_mode: bool
+    }
+
+impl DebugView {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Shaded => Self::Wireframe,
+            Self::Wireframe => Self::Normals,
+            Self::Normals => Self::TexCoords,
+            Self::TexCoords => Self::Depth,
+            Self::Depth => Self::InstanceId,
+            Self::InstanceId => Self::Shaded
+            }
+        }
+
+    // Shaded views draw the scene with its textures
+    pub const fn is_shaded(self) -> bool {
+        matches!(self, Self::Shaded | Self::Wireframe)
+        }
+    }
+
+impl DebugViewRenderer {
+    pub fn new(device: &Device, format: TextureFormat, camera_layout: &BindGroupLayout) -> Self {
+        let is_line_mode = device.features().contains(Features::POLYGON_MODE_LINE);
+        if ! is_line_mode {
+            info!("Line polygon mode is not supported, wireframes are drawn by a shader");
+            }
+
+        let shader = device.create_shader_module(include_wgsl!("../shaders/debug_view.wgsl"));
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Debug View Pipeline Layout"),
+            bind_group_layouts: &[
+                camera_layout
+                ],
+            push_constant_ranges: &[]
+            });
+
+        let create_pipeline = |label, fragment_entry, is_wireframe| {
+            let (vertex_entry, vertex_layout, polygon_mode) = match (is_wireframe, is_line_mode) {
+                (true, true) => ("vs_main", Vertex::DESC, PolygonMode::Line),
+                (true, false) => ("vs_barycentric", BarycentricVertex::DESC, PolygonMode::Fill),
+                (false, _) => ("vs_main", Vertex::DESC, PolygonMode::Fill)
+                };
+
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: Some(vertex_entry),
+                    compilation_options: PipelineCompilationOptions::default(),
+                    buffers: &[
+                        vertex_layout,
+                        InstanceRaw::DESC
+                        ]
+                    },
+                fragment: Some(FragmentState {
+                    module: &shader,
+                    entry_point: Some(fragment_entry),
+                    targets: &[
+                        Some(ColorTargetState {
+                            format,
+                            blend: Some(BlendState::ALPHA_BLENDING),
+                            write_mask: ColorWrites::ALL
+                            })
+                        ],
+                    compilation_options: PipelineCompilationOptions::default()
+                    }),
+                primitive: PrimitiveState {
+                    cull_mode: Some(Face::Back),
+                    polygon_mode,
+                    .. Default::default()
+                    },
+                // Wireframes lie on top of the already drawn surfaces, so they pass the test without writing
+                depth_stencil: Some(DepthStencilState {
+                    format: Texture::DEPTH_FORMAT,
+                    depth_write_enabled: ! is_wireframe,
+                    depth_compare: match is_wireframe {
+                        true => CompareFunction::LessEqual,
+                        false => CompareFunction::Less
+                        },
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default()
+                    }),
+                multisample: MultisampleState::default(),
+                multiview: None,
+                cache: None
+                })
+            };
+
+        Self {
+            device: device.clone(),
+            normals: create_pipeline("Normals Pipeline", "fs_normals", false),
+            tex_coords: create_pipeline("Texture Coordinates Pipeline", "fs_tex_coords", false),
+            depth: create_pipeline("Depth Pipeline", "fs_depth", false),
+            instance_id: create_pipeline("Instance ID Pipeline", "fs_instance", false),
+            wireframe: create_pipeline("Wireframe Pipeline", match is_line_mode {
+                true => "fs_wireframe",
+                false => "fs_barycentric"
+                }, true),
+            is_line_mode
+            }
+        }
+
+    // Draws the scene for the unshaded views, and the edges for the wireframe, shaded does nothing
+    pub fn draw(&self, view: DebugView, render_pass: &mut RenderPass, camera_bind_group: &BindGroup, scene: &Scene, assets: &Assets) {
+        let pipeline = match view {
+            DebugView::Shaded => return,
+            DebugView::Wireframe => &self.wireframe,
+            DebugView::Normals => &self.normals,
+            DebugView::TexCoords => &self.tex_coords,
+            DebugView::Depth => &self.depth,
+            DebugView::InstanceId => &self.instance_id
+            };
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+
+        scene.draw_meshes(render_pass, assets, |render_pass, mesh, instances| {
+            match view == DebugView::Wireframe && ! self.is_line_mode {
+                true => mesh.draw_barycentric(&self.device, render_pass, instances),
+                false => mesh.draw(render_pass, instances)
+                };
+            });
+        }
+    }
\ No newline at end of file
@@ -0,0 +1,112 @@
+use {
+    anyhow::Result as DynResult,
+    log::*,
+    notify::{
+        event::ModifyKind,
+        recommended_watcher,
+        Event,
+        EventKind,
+        RecommendedWatcher,
+        RecursiveMode,
+        Watcher
+        },
+    winit::event_loop::EventLoopProxy,
+    std::{
+        collections::HashMap,
+        path::{
+            Path,
+            PathBuf
+            },
+        sync::mpsc::{
+            channel,
+            Receiver,
+            RecvTimeoutError
+            },
+        thread,
+        time::{
+            Duration,
+            Instant
+            }
+        },
+    crate::app::AppEvent
+    };
+
+// Quiet time after the last write to a file, before it gets reloaded, saving often takes several writes
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+// Reports changed files through AppEvent::AssetChanged, with paths relative to the watched directory
+pub struct HotReload {
+    // Watching stops once dropped
+    _watcher: RecommendedWatcher
+    }
+
+impl HotReload {
+    pub fn new(dir: &Path, proxy: EventLoopProxy<AppEvent>) -> DynResult<Self> {
+        // Events come with absolute paths
+        let root = dir.canonicalize()?;
+
+        let (sender, receiver) = channel();
+        thread::spawn(move || debounce(receiver, proxy));
+
+        let mut watcher = recommended_watcher({
+            let root = root.clone();
+            move |result: notify::Result<Event>| {
+                let event = match result {
+                    Ok(event) => event,
+                    Err(e) => return error!("Unable to watch the assets {e}")
+                    };
+
+                // Metadata, like access times, changes without the contents, editors often save by renaming a new file over the old one
+                if ! matches!(event.kind, EventKind::Create(_) | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any)) {
+                    return;
+                    }
+
+                for path in event.paths {
+                    // Symlinks have to resolve into the root as well, paths renamed away no longer exist
+                    let path = path.canonicalize().unwrap_or(path);
+                    if let Ok(relative) = path.strip_prefix(&root) {
+                        let _ = sender.send(relative.to_owned());
+                        }
+                    }
+                }
+            })?;
+
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+        info!("Watching {} for changes", root.display());
+
+        Ok(Self {
+            _watcher: watcher
+            })
+        }
+    }
+
+// Sends every changed path once it stopped changing, ends when the watcher is dropped
+fn debounce(receiver: Receiver<PathBuf>, proxy: EventLoopProxy<AppEvent>) {
+    // Time at which every waiting path gets reported
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+
+    loop {
+        let result = match pending.values().min() {
+            Some(&deadline) => receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => receiver.recv()
+                .map_err(|_| RecvTimeoutError::Disconnected)
+            };
+
+        match result {
+            Ok(path) => {
+                pending.insert(path, Instant::now() + DEBOUNCE);
+                },
+            Err(RecvTimeoutError::Timeout) => {
+                let now = Instant::now();
+                pending.retain(|path, &mut deadline| {
+                    if deadline > now {
+                        return true;
+                        }
+                    let _ = proxy.send_event(AppEvent::AssetChanged(path.clone()));
+                    false
+                    });
+                },
+            Err(RecvTimeoutError::Disconnected) => return
+            };
+        }
+    }
@@ -0,0 +1,79 @@
+use {
+    winit::keyboard::KeyCode,
+    std::collections::HashMap
+    };
+
+// Discrete actions triggered by a single key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Exit,
+    Fullscreen,
+    Windowed,
+    SaveScene,
+    Screenshot,
+    ListAssets,
+    NextCameraController,
+    RecordKeyframe,
+    TogglePlayback,
+    RestartPlayback,
+    SlowerPlayback,
+    FasterPlayback,
+    SaveCameraPath,
+    NextPresentMode,
+    ExportStats,
+    ToggleDebugDraw,
+    NextDebugView
+    }
+
+pub struct KeyBindings {
+    bindings: HashMap<KeyCode, Action>
+    }
+
+impl KeyBindings {
+    pub fn new() -> Self {
+        Self {
+            bindings: HashMap::new()
+            }
+        }
+
+    // Replaces the previous action bound to the key, if there was any
+    pub fn bind(&mut self, key: KeyCode, action: Action) -> &mut Self {
+        self.bindings.insert(key, action);
+        self
+        }
+
+    pub fn unbind(&mut self, key: KeyCode) -> &mut Self {
+        self.bindings.remove(&key);
+        self
+        }
+
+    pub fn get_action(&self, key: KeyCode) -> Option<Action> {
+        self.bindings.get(&key)
+            .copied()
+        }
+    }
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let mut bindings = Self::new();
+        bindings
+            .bind(KeyCode::Escape, Action::Exit)
+            .bind(KeyCode::KeyF, Action::Fullscreen)
+            .bind(KeyCode::KeyE, Action::Windowed)
+            .bind(KeyCode::F5, Action::SaveScene)
+            .bind(KeyCode::F12, Action::Screenshot)
+            .bind(KeyCode::F2, Action::ListAssets)
+            .bind(KeyCode::KeyC, Action::NextCameraController)
+            .bind(KeyCode::KeyK, Action::RecordKeyframe)
+            .bind(KeyCode::KeyP, Action::TogglePlayback)
+            .bind(KeyCode::KeyR, Action::RestartPlayback)
+            .bind(KeyCode::BracketLeft, Action::SlowerPlayback)
+            .bind(KeyCode::BracketRight, Action::FasterPlayback)
+            .bind(KeyCode::F6, Action::SaveCameraPath)
+            .bind(KeyCode::KeyV, Action::NextPresentMode)
+            .bind(KeyCode::F7, Action::ExportStats)
+            .bind(KeyCode::KeyG, Action::ToggleDebugDraw)
+            .bind(KeyCode::F3, Action::NextDebugView);
+        bindings
+        }
+    }
\ No newline at end of file
@@ -25,6 +25,14 @@ impl Instance {
         Self { position, rotation }
         }
 
+    pub const fn get_position(&self) -> Vector3<f32> {
+        self.position
+        }
+
+    pub const fn get_rotation(&self) -> Quaternion<f32> {
+        self.rotation
+        }
+
     pub fn to_raw(&self) -> InstanceRaw {
         InstanceRaw {
             model: matrix4_to_array(Matrix4::from_translation(self.position) * Matrix4::from(self.rotation))
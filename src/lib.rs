@@ -0,0 +1,57 @@
+mod app;
+mod assets;
+mod camera;
+mod cinematic;
+mod controller;
+mod culling;
+mod debug_draw;
+mod debug_view;
+mod hot_reload;
+mod input;
+mod instance;
+mod light;
+mod mesh;
+pub mod pack;
+mod particles;
+mod picking;
+mod profiler;
+mod raycast;
+mod scene;
+mod screenshot;
+mod settings;
+mod source;
+mod state;
+mod stats;
+mod text;
+mod texture;
+mod upscale;
+mod utils;
+mod vertex;
+
+pub use {
+    app::{
+        App,
+        AppEvent
+        },
+    assets::{
+        Assets,
+        Handle
+        },
+    camera::Camera,
+    controller::CameraController,
+    input::{
+        Action,
+        KeyBindings
+        },
+    scene::{
+        Scene,
+        SceneDescription
+        },
+    state::State,
+    settings::{
+        RedrawMode,
+        Settings
+        },
+    texture::Texture,
+    utils::VertexInfo
+    };
\ No newline at end of file
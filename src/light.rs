@@ -0,0 +1,52 @@
+use {
+    bytemuck::{
+        Pod,
+        Zeroable
+        },
+    log::*,
+    crate::{
+        scene::LightDescription,
+        utils::*
+        }
+    };
+
+// Size of the light array inside of the shader, further lights are ignored
+pub const MAX_LIGHTS: usize = 8;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+struct LightRaw {
+    position: Vec3<f32>,
+    intensity: f32,
+    color: Vec3<f32>,
+    _padding: u32
+    }
+
+// Point lights of the scene, shading is skipped when there are none
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+pub struct LightUniform {
+    lights: [LightRaw; MAX_LIGHTS],
+    count: u32,
+    _padding: [u32; 3]
+    }
+
+impl LightUniform {
+    pub fn new(lights: &[LightDescription]) -> Self {
+        if lights.len() > MAX_LIGHTS {
+            warn!("Only the first {MAX_LIGHTS} of the {} lights are used", lights.len());
+            }
+
+        let mut uniform = Self::default();
+        for (raw, light) in uniform.lights.iter_mut().zip(lights) {
+            *raw = LightRaw {
+                position: light.position,
+                intensity: light.intensity,
+                color: light.color,
+                _padding: 0
+                };
+            }
+        uniform.count = lights.len().min(MAX_LIGHTS) as u32;
+        uniform
+        }
+    }
@@ -1,13 +1,5 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod app;
-mod camera;
-mod instance;
-mod state;
-mod texture;
-mod utils;
-mod vertex;
-
 use {
     anyhow::Result as DynResult,
     env_logger::{
@@ -16,7 +8,19 @@ use {
         Target
         },
     winit::event_loop::EventLoop,
-    crate::app::App
+    std::{
+        env::args_os,
+        path::{
+            Path,
+            PathBuf
+            }
+        },
+    wgpu_practice::{
+        App,
+        AppEvent,
+        KeyBindings,
+        Settings
+        }
     };
 
 fn main() -> DynResult<()> {
@@ -25,10 +29,17 @@ fn main() -> DynResult<()> {
         .write_style(WriteStyle::Auto)
         .init();
 
-    let event_loop = EventLoop::with_user_event()
+    let event_loop = EventLoop::<AppEvent>::with_user_event()
         .build()?;
 
-    let mut app = App::new();
+    let settings = Settings::load_or_default(Path::new("settings.ron"));
+
+    // Optional path to a scene file
+    let scene_path = args_os()
+        .nth(1)
+        .map(PathBuf::from);
+
+    let mut app = App::new(event_loop.create_proxy(), settings, scene_path, KeyBindings::default());
 
     event_loop.run_app(&mut app)?;
 
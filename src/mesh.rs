@@ -0,0 +1,141 @@
+use {
+    anyhow::Result as DynResult,
+    bytemuck::cast_slice,
+    cgmath::Point3,
+    serde::{
+        Deserialize,
+        Serialize
+        },
+    wgpu::{
+        *,
+        util::*
+        },
+    std::{
+        ops::Range,
+        sync::OnceLock
+        },
+    crate::vertex::{
+        BarycentricVertex,
+        Vertex
+        }
+    };
+
+// CPU side geometry, also used as the format of mesh files
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>
+    }
+
+pub struct Mesh {
+    data: MeshData,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    // Corners of the box around the vertices, for ray casting
+    bounds: (Point3<f32>, Point3<f32>),
+    // Unindexed copy of the triangles, only created once drawn with draw_barycentric
+    barycentric_buffer: OnceLock<Buffer>
+    }
+
+impl MeshData {
+    pub fn parse(text: &str) -> DynResult<Self> {
+        Ok(ron::from_str(text)?)
+        }
+
+    // Empty meshes get an inverted box, which no ray can hit
+    pub fn compute_bounds(&self) -> (Point3<f32>, Point3<f32>) {
+        let mut min = Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+
+        for vertex in &self.vertices {
+            let [x, y, z] = vertex.get_position();
+            min = Point3::new(min.x.min(x), min.y.min(y), min.z.min(z));
+            max = Point3::new(max.x.max(x), max.y.max(y), max.z.max(z));
+            }
+
+        (min, max)
+        }
+    }
+
+impl Mesh {
+    pub fn new(device: &Device, label: &str, data: MeshData) -> Self {
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&format!("{label} Vertex Buffer")),
+            contents: cast_slice(&data.vertices),
+            usage: BufferUsages::VERTEX
+            });
+
+        let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&format!("{label} Index Buffer")),
+            contents: cast_slice(&data.indices),
+            usage: BufferUsages::INDEX
+            });
+
+        let bounds = data.compute_bounds();
+
+        Self { data, vertex_buffer, index_buffer, bounds, barycentric_buffer: OnceLock::new() }
+        }
+
+    pub fn get_data(&self) -> &MeshData {
+        &self.data
+        }
+
+    pub const fn get_bounds(&self) -> (Point3<f32>, Point3<f32>) {
+        self.bounds
+        }
+
+    pub fn get_memory_size(&self) -> u64 {
+        self.vertex_buffer.size() + self.index_buffer.size()
+            + self.barycentric_buffer.get().map_or(0, Buffer::size)
+        }
+
+    pub const fn get_num_indices(&self) -> u32 {
+        self.data.indices.len() as u32
+        }
+
+    pub fn draw(&self, render_pass: &mut RenderPass, instances: Range<u32>) {
+        // Empty buffers can not be bound, which is the case for placeholders
+        if self.data.indices.is_empty() {
+            return;
+            }
+
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
+        render_pass.draw_indexed(0 .. self.get_num_indices(), 0, instances);
+        }
+
+    // Counts are read from the buffer at the offset, as written by the GPU
+    pub fn draw_indirect(&self, render_pass: &mut RenderPass, indirect_buffer: &Buffer, offset: BufferAddress) {
+        if self.data.indices.is_empty() {
+            return;
+            }
+
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
+        render_pass.draw_indexed_indirect(indirect_buffer, offset);
+        }
+
+    // Every corner of a triangle gets its own vertex, so shaders can tell how close a pixel is to the edges
+    pub fn draw_barycentric(&self, device: &Device, render_pass: &mut RenderPass, instances: Range<u32>) {
+        if self.data.indices.is_empty() {
+            return;
+            }
+
+        let buffer = self.barycentric_buffer.get_or_init(|| {
+            let corners = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+            let vertices: Vec<_> = self.data.indices.iter()
+                .zip(corners.iter().cycle())
+                .map(|(&index, &corner)| BarycentricVertex::new(self.data.vertices[index as usize], corner))
+                .collect();
+
+            device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Barycentric Vertex Buffer"),
+                contents: cast_slice(&vertices),
+                usage: BufferUsages::VERTEX
+                })
+            });
+
+        render_pass.set_vertex_buffer(0, buffer.slice(..));
+        render_pass.draw(0 .. self.get_num_indices(), instances);
+        }
+    }
\ No newline at end of file
@@ -0,0 +1,220 @@
+use {
+    anyhow::{
+        anyhow,
+        bail,
+        Context,
+        Result as DynResult
+        },
+    std::{
+        collections::HashMap,
+        fs::{
+            self,
+            File
+            },
+        io::{
+            BufReader,
+            BufWriter,
+            Read,
+            Seek,
+            SeekFrom,
+            Write
+            },
+        path::{
+            Component,
+            Path,
+            PathBuf
+            }
+        }
+    };
+
+// Layout: magic, version, entry count, index of (path, offset, size), blobs, numbers are little-endian
+const MAGIC: &[u8; 4] = b"WGPK";
+const VERSION: u32 = 1;
+
+// Single-file archive of assets, blobs are read on demand
+pub struct Pack {
+    path: PathBuf,
+    entries: HashMap<String, (u64, u64)>
+    }
+
+impl Pack {
+    pub fn open(path: &Path) -> DynResult<Self> {
+        let mut reader = BufReader::new(
+            File::open(path)
+                .with_context(|| format!("Unable to open pack {}", path.display()))?
+            );
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            bail!("{} is not an asset pack", path.display());
+            }
+
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            bail!("Unsupported pack version {version}");
+            }
+
+        let count = read_u32(&mut reader)?;
+        let mut entries = HashMap::with_capacity(count as usize);
+        for _ in 0 .. count {
+            let mut name = vec![0; read_u32(&mut reader)? as usize];
+            reader.read_exact(&mut name)?;
+            let offset = read_u64(&mut reader)?;
+            let size = read_u64(&mut reader)?;
+
+            entries.insert(String::from_utf8(name)?, (offset, size));
+            }
+
+        Ok(Self {
+            path: path.to_owned(),
+            entries
+            })
+        }
+
+    // Each read opens the file again, so it can be done from many threads at once
+    pub fn read(&self, path: &Path) -> DynResult<Vec<u8>> {
+        let &(offset, size) = self.entries.get(&entry_name(path))
+            .ok_or_else(|| anyhow!("{} is not in the pack {}", path.display(), self.path.display()))?;
+
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut bytes = vec![0; size as usize];
+        file.read_exact(&mut bytes)?;
+
+        Ok(bytes)
+        }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.entries.contains_key(&entry_name(path))
+        }
+
+    pub fn get_entries(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys()
+            .map(String::as_str)
+        }
+    }
+
+// Packs every file inside of the directory, apart from an older output, returns the number of entries
+pub fn write_pack(dir: &Path, output: &Path) -> DynResult<usize> {
+    let mut files = Vec::new();
+    collect_files(dir, dir, &mut files)?;
+    // Without an existing output there's nothing to skip, it is created after the files are collected
+    if let Ok(output) = output.canonicalize() {
+        files.retain(|file| dir.join(file).canonicalize().ok().as_ref() != Some(&output));
+        }
+    files.sort();
+
+    let names: Vec<_> = files.iter()
+        .map(|file| entry_name(file))
+        .collect();
+
+    let index_size: u64 = names.iter()
+        .map(|name| 4 + name.len() as u64 + 8 + 8)
+        .sum();
+
+    let mut offset = (MAGIC.len() + 4 + 4) as u64 + index_size;
+    let mut sizes = Vec::with_capacity(files.len());
+    for file in &files {
+        sizes.push(fs::metadata(dir.join(file))?.len());
+        }
+
+    let mut writer = BufWriter::new(
+        File::create(output)
+            .with_context(|| format!("Unable to create pack {}", output.display()))?
+        );
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&(files.len() as u32).to_le_bytes())?;
+
+    for (name, size) in names.iter().zip(&sizes) {
+        writer.write_all(&(name.len() as u32).to_le_bytes())?;
+        writer.write_all(name.as_bytes())?;
+        writer.write_all(&offset.to_le_bytes())?;
+        writer.write_all(&size.to_le_bytes())?;
+        offset += size;
+        }
+
+    for file in &files {
+        writer.write_all(&fs::read(dir.join(file))?)?;
+        }
+
+    writer.flush()?;
+
+    Ok(files.len())
+    }
+
+// Paths inside of the pack always use forward slashes
+fn entry_name(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None
+            })
+        .collect::<Vec<_>>()
+        .join("/")
+    }
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> DynResult<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        match path.is_dir() {
+            true => collect_files(root, &path, files)?,
+            false => files.push(path.strip_prefix(root)?.to_owned())
+            };
+        }
+
+    Ok(())
+    }
+
+fn read_u32(reader: &mut impl Read) -> DynResult<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+    }
+
+fn read_u64(reader: &mut impl Read) -> DynResult<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+    }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{
+            env,
+            process
+            }
+        };
+
+    #[test]
+    fn packs_read_back_what_was_written() {
+        let dir = env::temp_dir().join(format!("wgpu-practice-pack-{}", process::id()));
+        fs::create_dir_all(dir.join("models")).unwrap();
+        fs::write(dir.join("texture.png"), b"texture").unwrap();
+        fs::write(dir.join("models").join("cube.obj"), b"v 0 0 0").unwrap();
+        fs::write(dir.join("empty.txt"), b"").unwrap();
+
+        // Output inside of the packed directory, which gets written twice
+        let output = dir.join("assets.pack");
+        assert_eq!(write_pack(&dir, &output).unwrap(), 3);
+        assert_eq!(write_pack(&dir, &output).unwrap(), 3);
+
+        let pack = Pack::open(&output).unwrap();
+        let mut entries: Vec<_> = pack.get_entries().collect();
+        entries.sort_unstable();
+        assert_eq!(entries, ["empty.txt", "models/cube.obj", "texture.png"]);
+
+        assert_eq!(pack.read(Path::new("texture.png")).unwrap(), b"texture");
+        assert_eq!(pack.read(Path::new("models/cube.obj")).unwrap(), b"v 0 0 0");
+        assert!(pack.read(Path::new("empty.txt")).unwrap().is_empty());
+        assert!(! pack.contains(Path::new("assets.pack")));
+        assert!(pack.read(Path::new("missing.png")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+        }
+    }
\ No newline at end of file
@@ -0,0 +1,801 @@
+use {
+    anyhow::{
+        bail,
+        Result as DynResult
+        },
+    bytemuck::{
+        cast_slice,
+        Pod,
+        Zeroable
+        },
+    cgmath::*,
+    image::RgbaImage,
+    log::*,
+    serde::{
+        Deserialize,
+        Serialize
+        },
+    wgpu::*,
+    std::{
+        mem::size_of,
+        time::Duration
+        },
+    crate::{
+        camera::Camera,
+        profiler::GpuProfiler,
+        texture::Texture,
+        utils::*
+        }
+    };
+
+// Particles simulated by a single invocation group of the compute shader
+const WORKGROUP_SIZE: u32 = 64;
+// Emitters asking for more are cut off, as every particle is kept for the whole lifetime of the system
+const MAX_PARTICLES: usize = 1 << 20;
+// Lifetimes are divided by, so they can't reach zero
+const MIN_LIFETIME: f32 = 0.01;
+
+// Continuous source of particles, part of the scene description
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticleEmitter {
+    pub position: Vec3<f32>,
+    pub velocity: Vec3<f32>,
+    // Largest random offset of the velocity along every axis
+    pub spread: f32,
+    // Particles emitted per second
+    pub rate: f32,
+    // Seconds before a particle gets emitted again
+    pub lifetime: f32
+    }
+
+// Layout shared with the shaders, particles which are not emitted yet have a negative age
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct Particle {
+    position: Vec3<f32>,
+    age: f32,
+    velocity: Vec3<f32>,
+    lifetime: f32,
+    emitter: u32,
+    _padding: [u32; 3]
+    }
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct EmitterRaw {
+    position: Vec3<f32>,
+    spread: f32,
+    velocity: Vec3<f32>,
+    lifetime: f32
+    }
+
+// Billboard orientation for rendering, and the step for the simulation
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+struct ParticleUniform {
+    right: Vec3<f32>,
+    size: f32,
+    up: Vec3<f32>,
+    delta: f32,
+    gravity: Vec3<f32>,
+    seed: u32,
+    count: u32,
+    _padding: [u32; 3]
+    }
+
+// Where the simulation runs, the CPU keeps the results readable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleBackend {
+    Gpu,
+    Cpu
+    }
+
+// Only created for ParticleBackend::Gpu, as devices without compute shaders can't create them
+struct ParticleCompute {
+    layout: BindGroupLayout,
+    pipeline: ComputePipeline
+    }
+
+// Buffers recreated whenever the emitters change
+struct ParticleBuffers {
+    particles: Buffer,
+    compute_bind_group: Option<BindGroup>
+    }
+
+pub struct ParticleSystem {
+    device: Device,
+    backend: ParticleBackend,
+    emitters: Vec<ParticleEmitter>,
+    // Only kept up to date by the CPU backend, the GPU one only uploads it
+    particles: Vec<Particle>,
+    buffers: Option<ParticleBuffers>,
+    is_dirty: bool,
+    gravity: Vec3<f32>,
+    size: f32,
+    // Time simulated during the next prepare
+    pending_time: Duration,
+    seed: u32,
+    uniform_buffer: Buffer,
+    render_layout: BindGroupLayout,
+    render_bind_group: BindGroup,
+    render_pipeline: RenderPipeline,
+    compute: Option<ParticleCompute>
+    }
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            velocity: [0.0, 2.0, 0.0],
+            spread: 0.5,
+            rate: 20.0,
+            lifetime: 2.0
+            }
+        }
+    }
+
+impl ParticleEmitter {
+    // Rejects what the simulation can't run, like an infinite rate, or a lifetime which is NaN
+    pub fn validate(&self) -> DynResult<()> {
+        let values = self.position.into_iter()
+            .chain(self.velocity)
+            .chain([self.spread, self.rate, self.lifetime]);
+
+        for value in values {
+            if ! value.is_finite() {
+                bail!("Particle emitter has a value which is not finite");
+                }
+            }
+
+        Ok(())
+        }
+
+    // Particles alive at the same time, once the emitter runs steadily
+    pub fn get_capacity(&self) -> usize {
+        match self.rate > 0.0 {
+            true => (self.rate * self.get_lifetime()).ceil() as usize,
+            false => 0
+            }
+        }
+
+    fn get_lifetime(&self) -> f32 {
+        self.lifetime.max(MIN_LIFETIME)
+        }
+
+    fn to_raw(self) -> EmitterRaw {
+        EmitterRaw {
+            position: self.position,
+            spread: self.spread,
+            velocity: self.velocity,
+            lifetime: self.get_lifetime()
+            }
+        }
+    }
+
+impl Particle {
+    pub const fn get_position(&self) -> Vec3<f32> {
+        self.position
+        }
+
+    pub const fn get_velocity(&self) -> Vec3<f32> {
+        self.velocity
+        }
+
+    pub const fn get_age(&self) -> f32 {
+        self.age
+        }
+
+    pub const fn get_emitter(&self) -> usize {
+        self.emitter as usize
+        }
+
+    pub const fn is_emitted(&self) -> bool {
+        self.age >= 0.0
+        }
+    }
+
+impl VertexInfo for Particle {
+    // Read once per billboard, the velocity and the emitter only matter for the simulation
+    const DESC: VertexBufferLayout<'static> = VertexBufferLayout {
+        array_stride: size_of::<Self>() as BufferAddress,
+        step_mode: VertexStepMode::Instance,
+        attributes: &[
+            VertexAttribute {
+                offset: 0,
+                shader_location: 0,
+                format: VertexFormat::Float32x3
+                },
+            VertexAttribute {
+                offset: size_of::<Vec3<f32>>() as BufferAddress,
+                shader_location: 1,
+                format: VertexFormat::Float32
+                },
+            VertexAttribute {
+                offset: (size_of::<Vec3<f32>>() * 2 + size_of::<f32>()) as BufferAddress,
+                shader_location: 2,
+                format: VertexFormat::Float32
+                }
+            ]
+        };
+    }
+
+impl ParticleCompute {
+    fn new(device: &Device) -> Self {
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Particle Compute Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                        },
+                    count: None
+                    },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                        },
+                    count: None
+                    },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                        },
+                    count: None
+                    }
+                ]
+            });
+
+        let shader = device.create_shader_module(include_wgsl!("../shaders/particle_update.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Particle Compute Pipeline Layout"),
+            bind_group_layouts: &[
+                &layout
+                ],
+            push_constant_ranges: &[]
+            });
+
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Particle Compute Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("cs_main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None
+            });
+
+        Self { layout, pipeline }
+        }
+    }
+
+impl ParticleSystem {
+    // Billboards are drawn with the camera bind group at index 0, a soft dot is used until a texture is set
+    pub fn new(device: &Device, queue: &Queue, format: TextureFormat, camera_layout: &BindGroupLayout, backend: ParticleBackend) -> Self {
+        let uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Particle Uniform Buffer"),
+            size: size_of::<ParticleUniform>() as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false
+            });
+
+        let render_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Particle Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false
+                        },
+                    count: None
+                    },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None
+                    },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                        },
+                    count: None
+                    }
+                ]
+            });
+
+        let shader = device.create_shader_module(include_wgsl!("../shaders/particles.wgsl"));
+
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Particle Pipeline Layout"),
+            bind_group_layouts: &[
+                camera_layout,
+                &render_layout
+                ],
+            push_constant_ranges: &[]
+            });
+
+        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Particle Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[
+                    Particle::DESC
+                    ]
+                },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[
+                    Some(ColorTargetState {
+                        format,
+                        blend: Some(BlendState::ALPHA_BLENDING),
+                        write_mask: ColorWrites::ALL
+                        })
+                    ],
+                compilation_options: PipelineCompilationOptions::default()
+                }),
+            // Billboards always face the camera, so there is no back to cull
+            primitive: PrimitiveState::default(),
+            // Particles are not sorted, so they don't write depth to avoid hiding each other
+            depth_stencil: Some(DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::LessEqual,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default()
+                }),
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None
+            });
+
+        let compute = match backend {
+            ParticleBackend::Gpu => Some(ParticleCompute::new(device)),
+            ParticleBackend::Cpu => None
+            };
+
+        let texture = Texture::from_rgba(device, queue, &create_dot_image(32), Some("Particle Texture"));
+        let render_bind_group = create_render_bind_group(device, &render_layout, &texture, &uniform_buffer);
+
+        Self {
+            device: device.clone(),
+            backend,
+            emitters: Vec::new(),
+            particles: Vec::new(),
+            buffers: None,
+            is_dirty: false,
+            gravity: [0.0, - 9.81, 0.0],
+            size: 0.1,
+            pending_time: Duration::ZERO,
+            seed: 0,
+            uniform_buffer,
+            render_layout,
+            render_bind_group,
+            render_pipeline,
+            compute
+            }
+        }
+
+    // Restarts every emitter, as the particles are laid out by their emitters
+    pub fn set_emitters(&mut self, emitters: &[ParticleEmitter]) {
+        self.emitters = emitters.to_vec();
+        self.particles = spawn_particles(&self.emitters);
+        self.is_dirty = true;
+        }
+
+    // Restarts the other emitters too
+    pub fn add_emitter(&mut self, emitter: ParticleEmitter) -> &mut Self {
+        self.emitters.push(emitter);
+        self.particles = spawn_particles(&self.emitters);
+        self.is_dirty = true;
+        self
+        }
+
+    pub fn get_emitters(&self) -> &[ParticleEmitter] {
+        &self.emitters
+        }
+
+    // Current state with ParticleBackend::Cpu, the initial one with ParticleBackend::Gpu
+    pub fn get_particles(&self) -> &[Particle] {
+        &self.particles
+        }
+
+    pub const fn get_backend(&self) -> ParticleBackend {
+        self.backend
+        }
+
+    pub const fn set_gravity(&mut self, gravity: Vec3<f32>) -> &mut Self {
+        self.gravity = gravity;
+        self
+        }
+
+    // Width, and height of the billboards in world units
+    pub const fn set_size(&mut self, size: f32) -> &mut Self {
+        self.size = size;
+        self
+        }
+
+    pub fn set_texture(&mut self, texture: &Texture) -> &mut Self {
+        self.render_bind_group = create_render_bind_group(&self.device, &self.render_layout, texture, &self.uniform_buffer);
+        self
+        }
+
+    // Time is collected, and simulated all at once during the next prepare
+    pub fn advance(&mut self, dt: Duration) {
+        if ! self.emitters.is_empty() {
+            self.pending_time += dt;
+            }
+        }
+
+    // Runs the simulation, either by recording the compute pass, or right away on the CPU
+    pub fn prepare(&mut self, queue: &Queue, encoder: &mut CommandEncoder, camera: &Camera, profiler: Option<&mut GpuProfiler>) {
+        if self.is_dirty {
+            self.buffers = self.create_buffers();
+            self.is_dirty = false;
+            }
+
+        let Some(buffers) = &self.buffers else {
+            return;
+            };
+
+        let delta = self.pending_time.as_secs_f32();
+        self.pending_time = Duration::ZERO;
+        self.seed = self.seed.wrapping_add(1);
+
+        let forward = (camera.get_target() - camera.get_eye()).normalize();
+        // Looking straight up or down leaves no right axis next to the world's up, so the depth axis replaces it
+        let right = match forward.cross(Vector3::unit_y()) {
+            right if right.magnitude2() > f32::EPSILON => right.normalize(),
+            _ => forward.cross(Vector3::unit_z()).normalize()
+            };
+        let up = right.cross(forward);
+
+        let uniform = ParticleUniform {
+            right: right.into(),
+            size: self.size,
+            up: up.into(),
+            delta,
+            gravity: self.gravity,
+            seed: self.seed,
+            count: self.particles.len() as u32,
+            .. Default::default()
+            };
+        queue.write_buffer(&self.uniform_buffer, 0, cast_slice(&[uniform]));
+
+        if delta == 0.0 {
+            return;
+            }
+
+        match (&self.compute, &buffers.compute_bind_group) {
+            (Some(compute), Some(compute_bind_group)) => {
+                let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("Particle Pass"),
+                    timestamp_writes: profiler.and_then(|profiler| profiler.compute_pass_timestamps("Particles"))
+                    });
+
+                compute_pass.set_pipeline(&compute.pipeline);
+                compute_pass.set_bind_group(0, compute_bind_group, &[]);
+                compute_pass.dispatch_workgroups(uniform.count.div_ceil(WORKGROUP_SIZE), 1, 1);
+                },
+            _ => {
+                simulate(&mut self.particles, &self.emitters, self.gravity, delta, self.seed);
+                queue.write_buffer(&buffers.particles, 0, cast_slice(&self.particles));
+                }
+            };
+        }
+
+    // The camera bind group has to be set already
+    pub fn draw(&self, render_pass: &mut RenderPass) {
+        let Some(buffers) = &self.buffers else {
+            return;
+            };
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(1, &self.render_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, buffers.particles.slice(..));
+        render_pass.draw(0 .. 6, 0 .. self.particles.len() as u32);
+        }
+
+    // Empty buffers can not be bound, so there are none without particles
+    fn create_buffers(&self) -> Option<ParticleBuffers> {
+        if self.particles.is_empty() {
+            return None;
+            }
+
+        let particles = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Particle Buffer"),
+            size: (self.particles.len() * size_of::<Particle>()) as BufferAddress,
+            usage: match self.compute {
+                Some(_) => BufferUsages::VERTEX | BufferUsages::STORAGE,
+                None => BufferUsages::VERTEX | BufferUsages::COPY_DST
+                },
+            mapped_at_creation: true
+            });
+        particles.slice(..)
+            .get_mapped_range_mut()
+            .copy_from_slice(cast_slice(&self.particles));
+        particles.unmap();
+
+        let compute_bind_group = self.compute.as_ref().map(|compute| {
+            let emitters_data: Vec<_> = self.emitters.iter()
+                .map(|emitter| emitter.to_raw())
+                .collect();
+
+            let emitters = self.device.create_buffer(&BufferDescriptor {
+                label: Some("Particle Emitter Buffer"),
+                size: (emitters_data.len() * size_of::<EmitterRaw>()) as BufferAddress,
+                usage: BufferUsages::STORAGE,
+                mapped_at_creation: true
+                });
+            emitters.slice(..)
+                .get_mapped_range_mut()
+                .copy_from_slice(cast_slice(&emitters_data));
+            emitters.unmap();
+
+            self.device.create_bind_group(&BindGroupDescriptor {
+                label: Some("Particle Compute Bind Group"),
+                layout: &compute.layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: self.uniform_buffer.as_entire_binding()
+                        },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: particles.as_entire_binding()
+                        },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: emitters.as_entire_binding()
+                        }
+                    ]
+                })
+            });
+
+        Some(ParticleBuffers {
+            particles,
+            compute_bind_group
+            })
+        }
+    }
+
+// CPU version of the compute shader, particles are emitted again once their lifetime runs out
+pub fn simulate(particles: &mut [Particle], emitters: &[ParticleEmitter], gravity: Vec3<f32>, delta: f32, seed: u32) {
+    let gravity = Vector3::from(gravity);
+    let seed = hash(seed);
+
+    for (index, particle) in particles.iter_mut().enumerate() {
+        let previous_age = particle.age;
+        particle.age += delta;
+
+        // Negative ages wait for their turn to be emitted
+        if particle.age < 0.0 {
+            continue;
+            }
+
+        let mut velocity = Vector3::from(particle.velocity);
+        let mut position = Vector3::from(particle.position);
+
+        if previous_age < 0.0 || particle.age >= particle.lifetime {
+            let emitter = emitters[particle.emitter as usize].to_raw();
+            let index_seed = hash(index as u32 ^ seed);
+            let direction = Vector3::new(random(index_seed), random(index_seed.wrapping_add(1)), random(index_seed.wrapping_add(2))) * 2.0
+                - Vector3::new(1.0, 1.0, 1.0);
+
+            particle.age %= emitter.lifetime;
+            particle.lifetime = emitter.lifetime;
+            position = emitter.position.into();
+            velocity = Vector3::from(emitter.velocity) + direction * emitter.spread;
+            }
+
+        velocity += gravity * delta;
+        position += velocity * delta;
+        particle.velocity = velocity.into();
+        particle.position = position.into();
+        }
+    }
+
+// Emission of every emitter is spread over its first lifetime, instead of starting with a burst
+fn spawn_particles(emitters: &[ParticleEmitter]) -> Vec<Particle> {
+    let mut particles = Vec::new();
+
+    for (index, emitter) in emitters.iter().enumerate() {
+        let capacity = emitter.get_capacity();
+        // Subtracting instead of adding, as the capacity reaches usize::MAX for huge rates
+        if capacity > MAX_PARTICLES - particles.len() {
+            warn!("Particle emitter {index} exceeds the limit of {MAX_PARTICLES} particles, and was skipped");
+            continue;
+            }
+
+        particles.extend((0 .. capacity).map(|i| Particle {
+            position: emitter.position,
+            // Even the first particle starts out waiting, so it gets its velocity once emitted
+            age: - ((i + 1) as f32) / emitter.rate,
+            velocity: [0.0; 3],
+            lifetime: emitter.get_lifetime(),
+            emitter: index as u32,
+            _padding: [0; 3]
+            }));
+        }
+
+    particles
+    }
+
+fn create_render_bind_group(device: &Device, layout: &BindGroupLayout, texture: &Texture, uniform_buffer: &Buffer) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Particle Bind Group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(texture.get_view())
+                },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::Sampler(texture.get_sampler())
+                },
+            BindGroupEntry {
+                binding: 2,
+                resource: uniform_buffer.as_entire_binding()
+                }
+            ]
+        })
+    }
+
+// White circle, which fades out towards the edge
+fn create_dot_image(size: u32) -> RgbaImage {
+    RgbaImage::from_fn(size, size, |x, y| {
+        let center = size as f32 * 0.5;
+        let distance = Vector2::new(x as f32 + 0.5 - center, y as f32 + 0.5 - center).magnitude() / center;
+        let alpha = (1.0 - distance).clamp(0.0, 1.0);
+        [255, 255, 255, (alpha * 255.0) as u8].into()
+        })
+    }
+
+// PCG hash, matches the compute shader
+const fn hash(value: u32) -> u32 {
+    let state = value.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+    }
+
+fn random(seed: u32) -> f32 {
+    hash(seed) as f32 / u32::MAX as f32
+    }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAVITY: Vec3<f32> = [0.0, -10.0, 0.0];
+
+    fn emitter(rate: f32, lifetime: f32) -> ParticleEmitter {
+        ParticleEmitter {
+            position: [1.0, 2.0, 3.0],
+            velocity: [0.0, 5.0, 0.0],
+            spread: 0.0,
+            rate,
+            lifetime
+            }
+        }
+
+    #[test]
+    fn particles_wait_for_their_emission() {
+        let emitters = [emitter(10.0, 1.0)];
+        let mut particles = spawn_particles(&emitters);
+        assert_eq!(particles.len(), 10);
+        assert!(particles.iter().all(|particle| ! particle.is_emitted()));
+
+        simulate(&mut particles, &emitters, GRAVITY, 0.15, 0);
+
+        assert!(particles[0].is_emitted());
+        assert_eq!(particles[0].get_velocity(), [0.0, 5.0 - 10.0 * 0.15, 0.0]);
+        assert!(particles[1 ..].iter().all(|particle| ! particle.is_emitted()));
+        // Waiting particles stay where they were spawned
+        assert_eq!(particles[1].get_position(), [1.0, 2.0, 3.0]);
+        assert_eq!(particles[1].get_velocity(), [0.0; 3]);
+        }
+
+    #[test]
+    fn particles_are_emitted_again_after_their_lifetime() {
+        let emitters = [emitter(1.0, 1.0)];
+        let mut particles = spawn_particles(&emitters);
+        assert_eq!(particles.len(), 1);
+
+        simulate(&mut particles, &emitters, GRAVITY, 1.0, 0);
+        simulate(&mut particles, &emitters, GRAVITY, 0.5, 0);
+        assert_eq!(particles[0].get_position(), [1.0, -8.0, 3.0]);
+
+        simulate(&mut particles, &emitters, GRAVITY, 0.75, 0);
+        let particle = particles[0];
+        assert_eq!(particle.get_age(), 0.25);
+        // Restarted at the emitter with its velocity, then moved for a single step
+        assert_eq!(particle.get_velocity(), [0.0, -2.5, 0.0]);
+        assert_eq!(particle.get_position(), [1.0, 0.125, 3.0]);
+        }
+
+    #[test]
+    fn simulation_integrates_gravity_and_velocity() {
+        let emitters = [emitter(1.0, 100.0)];
+        let mut particles = vec![Particle {
+            position: [0.0; 3],
+            age: 1.0,
+            velocity: [1.0, 2.0, 0.0],
+            lifetime: 100.0,
+            emitter: 0,
+            _padding: [0; 3]
+            }];
+
+        let delta = 0.1;
+        let (mut position, mut velocity) = (Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 0.0));
+        for _ in 0 .. 10 {
+            simulate(&mut particles, &emitters, GRAVITY, delta, 0);
+            velocity += Vector3::from(GRAVITY) * delta;
+            position += velocity * delta;
+            }
+
+        let particle = particles[0];
+        assert!((Vector3::from(particle.get_velocity()) - velocity).magnitude() < 1e-4);
+        assert!((Vector3::from(particle.get_position()) - position).magnitude() < 1e-4);
+        // Semi-implicit Euler, the velocity is updated before the position
+        assert!((velocity.y + 8.0).abs() < 1e-4);
+        assert!((position.y + 3.5).abs() < 1e-4);
+        }
+
+    #[test]
+    fn same_seed_gives_the_same_particles() {
+        let emitters = [ParticleEmitter::default()];
+        let run = |seed: u32| {
+            let mut particles = spawn_particles(&emitters);
+            for step in 0 .. 20 {
+                simulate(&mut particles, &emitters, GRAVITY, 0.1, seed.wrapping_add(step));
+                }
+            particles.iter()
+                .map(|particle| (particle.get_position(), particle.get_velocity(), particle.get_age()))
+                .collect::<Vec<_>>()
+            };
+
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+        }
+
+    #[test]
+    fn emitters_reject_values_which_are_not_finite() {
+        assert!(ParticleEmitter::default().validate().is_ok());
+        assert!(emitter(f32::INFINITY, 1.0).validate().is_err());
+        assert!(emitter(10.0, f32::NAN).validate().is_err());
+        }
+
+    #[test]
+    fn huge_emitters_are_skipped() {
+        let emitters = [emitter(1.0, 1.0), emitter(f32::MAX, 1.0), emitter(2.0, 1.0)];
+        assert_eq!(emitters[1].get_capacity(), usize::MAX);
+
+        let particles = spawn_particles(&emitters);
+        assert_eq!(particles.len(), 3);
+        assert!(particles.iter().all(|particle| particle.get_emitter() != 1));
+        }
+    }
@@ -0,0 +1,257 @@
+use {
+    anyhow::Result as DynResult,
+    log::*,
+    wgpu::*,
+    winit::event_loop::EventLoopProxy,
+    std::{
+        sync::mpsc::channel,
+        thread
+        },
+    crate::{
+        app::AppEvent,
+        assets::Assets,
+        instance::InstanceRaw,
+        scene::Scene,
+        texture::Texture,
+        utils::VertexInfo,
+        vertex::Vertex
+        }
+    };
+
+const ID_FORMAT: TextureFormat = TextureFormat::R32Uint;
+
+// Renders instance indices into an ID target, so the instance under a pixel can be read back
+pub struct Picker {
+    device: Device,
+    id_texture: Texture,
+    depth_texture: Texture,
+    id_pipeline: RenderPipeline,
+    highlight_pipeline: RenderPipeline
+    }
+
+// Copy of a single pixel of the ID target, which waits for the GPU to finish before it can be read
+pub struct PendingPick {
+    buffer: Buffer
+    }
+
+impl Picker {
+    // Size has to match the target the scene is rendered to
+    pub fn new(device: &Device, format: TextureFormat, camera_layout: &BindGroupLayout, width: u32, height: u32) -> Self {
+        let shader = device.create_shader_module(include_wgsl!("../shaders/picking.wgsl"));
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Picking Pipeline Layout"),
+            bind_group_layouts: &[
+                camera_layout
+                ],
+            push_constant_ranges: &[]
+            });
+
+        let create_pipeline = |label, fragment_entry, target: ColorTargetState, depth_stencil: DepthStencilState| device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[
+                    Vertex::DESC,
+                    InstanceRaw::DESC
+                    ]
+                },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some(fragment_entry),
+                targets: &[
+                    Some(target)
+                    ],
+                compilation_options: PipelineCompilationOptions::default()
+                }),
+            primitive: PrimitiveState {
+                cull_mode: Some(Face::Back),
+                .. Default::default()
+                },
+            depth_stencil: Some(depth_stencil),
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None
+            });
+
+        let id_pipeline = create_pipeline(
+            "Picking Pipeline",
+            "fs_id",
+            // Integer targets can't be blended
+            ColorTargetState {
+                format: ID_FORMAT,
+                blend: None,
+                write_mask: ColorWrites::ALL
+                },
+            DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Less,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default()
+                }
+            );
+
+        // Brightens the already drawn instance
+        let highlight_pipeline = create_pipeline(
+            "Highlight Pipeline",
+            "fs_highlight",
+            ColorTargetState {
+                format,
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add
+                        },
+                    alpha: BlendComponent::OVER
+                    }),
+                write_mask: ColorWrites::ALL
+                },
+            DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::LessEqual,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default()
+                }
+            );
+
+        Self {
+            device: device.clone(),
+            id_texture: Texture::create_render_target(device, width, height, ID_FORMAT, FilterMode::Nearest, Some("Picking ID Texture")),
+            depth_texture: Texture::create_depth_texture(device, width, height, Some("Picking Depth Texture")),
+            id_pipeline,
+            highlight_pipeline
+            }
+        }
+
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.id_texture = Texture::create_render_target(device, width, height, ID_FORMAT, FilterMode::Nearest, Some("Picking ID Texture"));
+        self.depth_texture = Texture::create_depth_texture(device, width, height, Some("Picking Depth Texture"));
+        }
+
+    // Records the ID pass, and the copy of the pixel at the given position of the render target
+    pub fn pick(&self, encoder: &mut CommandEncoder, camera_bind_group: &BindGroup, scene: &Scene, assets: &Assets, (x, y): (u32, u32), timestamp_writes: Option<RenderPassTimestampWrites>) -> PendingPick {
+        /* A mutable borrow of encoder needs to be dropped */ {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Picking Pass"),
+                color_attachments: &[
+                    Some(RenderPassColorAttachment {
+                        view: self.id_texture.get_view(),
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Clear(Color::TRANSPARENT),
+                            store: StoreOp::Store
+                            },
+                        depth_slice: None
+                        })
+                    ],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: self.depth_texture.get_view(),
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(1.0),
+                        store: StoreOp::Discard
+                        }),
+                    stencil_ops: None
+                    }),
+                occlusion_query_set: None,
+                timestamp_writes
+                });
+
+            render_pass.set_pipeline(&self.id_pipeline);
+            render_pass.set_bind_group(0, camera_bind_group, &[]);
+            scene.draw_meshes(&mut render_pass, assets, |render_pass, mesh, instances| mesh.draw(render_pass, instances));
+            }
+
+        // A single row doesn't need the padding, but the layout still has to be aligned
+        let buffer = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Picking Buffer"),
+            size: COPY_BYTES_PER_ROW_ALIGNMENT as BufferAddress,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false
+            });
+
+        let size = self.id_texture.get_texture().size();
+        encoder.copy_texture_to_buffer(
+            TexelCopyTextureInfo {
+                texture: self.id_texture.get_texture(),
+                mip_level: 0,
+                origin: Origin3d {
+                    x: x.min(size.width - 1),
+                    y: y.min(size.height - 1),
+                    z: 0
+                    },
+                aspect: TextureAspect::All
+                },
+            TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(COPY_BYTES_PER_ROW_ALIGNMENT),
+                    rows_per_image: Some(1)
+                    }
+                },
+            Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1
+                }
+            );
+
+        PendingPick {
+            buffer
+            }
+        }
+
+    // Draws the selected instance once more over the scene, has to be called inside of the main pass
+    pub fn draw_highlight(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup, scene: &Scene, assets: &Assets, selected: usize) {
+        let selected = selected as u32;
+
+        render_pass.set_pipeline(&self.highlight_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        scene.draw_meshes(render_pass, assets, |render_pass, mesh, instances| {
+            if instances.contains(&selected) {
+                mesh.draw(render_pass, selected .. selected + 1);
+                }
+            });
+        }
+    }
+
+impl PendingPick {
+    // Result arrives as AppEvent::InstancePicked, tagged with the given scene generation
+    pub fn read_in_background(self, device: Device, proxy: EventLoopProxy<AppEvent>, generation: u64) {
+        thread::spawn(move || {
+            match self.read(&device) {
+                Ok(slot) => {
+                    let _ = proxy.send_event(AppEvent::InstancePicked { slot, generation });
+                    },
+                Err(e) => error!("Unable to read the picked instance {e:#}")
+                };
+            });
+        }
+
+    fn read(self, device: &Device) -> DynResult<Option<usize>> {
+        let slice = self.buffer.slice(..);
+
+        let (sender, receiver) = channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+            });
+
+        device.poll(PollType::Wait)?;
+        receiver.recv()??;
+
+        let id = /* The mapped view needs to be dropped before unmapping */ {
+            let data = slice.get_mapped_range();
+            u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+            };
+        self.buffer.unmap();
+
+        // Zero is the background
+        Ok(id.checked_sub(1).map(|index| index as usize))
+        }
+    }
\ No newline at end of file
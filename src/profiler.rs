@@ -0,0 +1,214 @@
+use {
+    bytemuck::cast_slice,
+    log::*,
+    wgpu::*,
+    std::sync::mpsc::{
+        channel,
+        Receiver,
+        Sender
+        }
+    };
+
+// Passes which can be timed within a single frame
+const MAX_PASSES: u32 = 8;
+// Frames which can wait for their readback at the same time, the rest go unmeasured
+const FRAMES_IN_FLIGHT: usize = 3;
+const TIMESTAMP_SIZE: BufferAddress = size_of::<u64>() as BufferAddress;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FrameState {
+    Free,
+    Recording,
+    // Submitted, and waiting for the readback buffer to be mapped
+    Pending
+    }
+
+struct ProfilerFrame {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    passes: Vec<&'static str>,
+    state: FrameState
+    }
+
+// Measures the GPU time of render, and compute passes with timestamp queries, needs Features::TIMESTAMP_QUERY
+pub struct GpuProfiler {
+    device: Device,
+    frames: Vec<ProfilerFrame>,
+    current: Option<usize>,
+    // Nanoseconds per timestamp tick
+    period: f32,
+    sender: Sender<(usize, Result<(), BufferAsyncError>)>,
+    receiver: Receiver<(usize, Result<(), BufferAsyncError>)>,
+    // Milliseconds of every pass from the latest measured frame
+    pass_times: Vec<(&'static str, f32)>
+    }
+
+impl GpuProfiler {
+    // Adapters without timestamp queries get no profiler
+    pub fn new(device: &Device, queue: &Queue) -> Option<Self> {
+        if ! device.features().contains(Features::TIMESTAMP_QUERY) {
+            info!("GPU profiling is unavailable, as timestamp queries are not supported");
+            return None;
+            }
+
+        let frames = (0 .. FRAMES_IN_FLIGHT)
+            .map(|_| ProfilerFrame {
+                query_set: device.create_query_set(&QuerySetDescriptor {
+                    label: Some("Profiler Query Set"),
+                    ty: QueryType::Timestamp,
+                    count: MAX_PASSES * 2
+                    }),
+                resolve_buffer: device.create_buffer(&BufferDescriptor {
+                    label: Some("Profiler Resolve Buffer"),
+                    size: MAX_PASSES as BufferAddress * 2 * TIMESTAMP_SIZE,
+                    usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+                    mapped_at_creation: false
+                    }),
+                readback_buffer: device.create_buffer(&BufferDescriptor {
+                    label: Some("Profiler Readback Buffer"),
+                    size: MAX_PASSES as BufferAddress * 2 * TIMESTAMP_SIZE,
+                    usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                    mapped_at_creation: false
+                    }),
+                passes: Vec::new(),
+                state: FrameState::Free
+                })
+            .collect();
+
+        let (sender, receiver) = channel();
+
+        Some(Self {
+            device: device.clone(),
+            frames,
+            current: None,
+            period: queue.get_timestamp_period(),
+            sender,
+            receiver,
+            pass_times: Vec::new()
+            })
+        }
+
+    // Collects finished readbacks, and picks a free set of queries for the new frame
+    pub fn begin_frame(&mut self) {
+        let _ = self.device.poll(PollType::Poll);
+        while let Ok((index, result)) = self.receiver.try_recv() {
+            self.read_frame(index, result);
+            }
+
+        // Frames which were never submitted can be reused
+        for frame in &mut self.frames {
+            if frame.state == FrameState::Recording {
+                frame.state = FrameState::Free;
+                }
+            }
+
+        self.current = self.frames.iter()
+            .position(|frame| frame.state == FrameState::Free);
+
+        if let Some(index) = self.current {
+            let frame = &mut self.frames[index];
+            frame.passes.clear();
+            frame.state = FrameState::Recording;
+            }
+        }
+
+    // None while every set of queries is still in use, or all of them were taken this frame
+    pub fn pass_timestamps(&mut self, name: &'static str) -> Option<RenderPassTimestampWrites<'_>> {
+        let (query_set, index) = self.reserve_queries(name)?;
+
+        Some(RenderPassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1)
+            })
+        }
+
+    // Same as pass_timestamps, the pass has to be recorded once they are taken
+    pub fn compute_pass_timestamps(&mut self, name: &'static str) -> Option<ComputePassTimestampWrites<'_>> {
+        let (query_set, index) = self.reserve_queries(name)?;
+
+        Some(ComputePassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1)
+            })
+        }
+
+    // Returns the query set of the current frame, and the first of the pass's two queries
+    fn reserve_queries(&mut self, name: &'static str) -> Option<(&QuerySet, u32)> {
+        let frame = &mut self.frames[self.current?];
+        if frame.passes.len() as u32 == MAX_PASSES {
+            warn!("Unable to time the {name} pass, as only {MAX_PASSES} passes can be timed");
+            return None;
+            }
+
+        let index = frame.passes.len() as u32 * 2;
+        frame.passes.push(name);
+        Some((&frame.query_set, index))
+        }
+
+    // Copies the timestamps into the readback buffer, has to be the last command of the frame
+    pub fn resolve(&mut self, encoder: &mut CommandEncoder) {
+        let Some(index) = self.current else {
+            return;
+            };
+        let frame = &self.frames[index];
+
+        let count = frame.passes.len() as u32 * 2;
+        if count == 0 {
+            return;
+            }
+
+        encoder.resolve_query_set(&frame.query_set, 0 .. count, &frame.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&frame.resolve_buffer, 0, &frame.readback_buffer, 0, count as BufferAddress * TIMESTAMP_SIZE);
+        }
+
+    // Starts reading the timestamps back, results arrive during the following frames
+    pub fn end_frame(&mut self) {
+        let Some(index) = self.current.take() else {
+            return;
+            };
+        let frame = &mut self.frames[index];
+
+        if frame.passes.is_empty() {
+            frame.state = FrameState::Free;
+            return;
+            }
+
+        let sender = self.sender.clone();
+        let size = frame.passes.len() as BufferAddress * 2 * TIMESTAMP_SIZE;
+        frame.readback_buffer.slice(.. size)
+            .map_async(MapMode::Read, move |result| {
+                let _ = sender.send((index, result));
+                });
+        frame.state = FrameState::Pending;
+        }
+
+    fn read_frame(&mut self, index: usize, result: Result<(), BufferAsyncError>) {
+        let frame = &mut self.frames[index];
+        frame.state = FrameState::Free;
+
+        if let Err(e) = result {
+            return warn!("Unable to read the GPU timestamps {e}");
+            }
+
+        let size = frame.passes.len() as BufferAddress * 2 * TIMESTAMP_SIZE;
+        /* The mapped view has to be dropped before unmapping */ {
+            let data = frame.readback_buffer.slice(.. size)
+                .get_mapped_range();
+            let timestamps: &[u64] = cast_slice(&data);
+
+            self.pass_times = frame.passes.iter()
+                .zip(timestamps.chunks_exact(2))
+                .map(|(&name, pair)| (name, pair[1].saturating_sub(pair[0]) as f32 * self.period / 1_000_000.0))
+                .collect();
+            }
+        frame.readback_buffer.unmap();
+        }
+
+    // Pairs of (pass name, milliseconds), from the latest frame which finished measuring
+    pub fn get_pass_times(&self) -> &[(&'static str, f32)] {
+        &self.pass_times
+        }
+    }
\ No newline at end of file
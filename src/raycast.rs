@@ -0,0 +1,203 @@
+use {
+    cgmath::*,
+    crate::utils::*
+    };
+
+// Hits closer than this are ignored, so rays starting on a surface don't hit it again
+const EPSILON: f32 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    origin: Point3<f32>,
+    // Always normalized, so distances along the ray are in world units
+    direction: Vector3<f32>
+    }
+
+// Closest intersection found by Scene::raycast
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub distance: f32,
+    pub position: Point3<f32>,
+    // Position of the instance inside of the scene file
+    pub instance: usize,
+    // Index of the first of the triangle's indices inside of the mesh
+    pub triangle: usize,
+    // Weights of the triangle's three vertices at the hit
+    pub barycentric: Vec3<f32>
+    }
+
+impl Ray {
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize()
+            }
+        }
+
+    pub const fn get_origin(&self) -> Point3<f32> {
+        self.origin
+        }
+
+    pub const fn get_direction(&self) -> Vector3<f32> {
+        self.direction
+        }
+
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+        }
+
+    // Moves the ray into the space of an instance, which only has a translation and a rotation
+    pub fn to_local(self, position: Vector3<f32>, rotation: Quaternion<f32>) -> Self {
+        let inverse = rotation.invert();
+        Self {
+            origin: Point3::from_vec(inverse.rotate_vector(self.origin.to_vec() - position)),
+            direction: inverse.rotate_vector(self.direction)
+            }
+        }
+
+    // Slab test, returns the distance where the ray enters the box, or zero when it starts inside
+    pub fn intersect_aabb(&self, min: Point3<f32>, max: Point3<f32>) -> Option<f32> {
+        // Inverted boxes are empty, but their infinite slabs would still overlap
+        if (0 .. 3).any(|axis| min[axis] > max[axis]) {
+            return None;
+            }
+
+        let mut near = 0.0_f32;
+        let mut far = f32::INFINITY;
+
+        for axis in 0 .. 3 {
+            // Division by zero gives infinities, which the comparisons handle
+            let inverse = 1.0 / self.direction[axis];
+            let t0 = (min[axis] - self.origin[axis]) * inverse;
+            let t1 = (max[axis] - self.origin[axis]) * inverse;
+
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+            if near > far {
+                return None;
+                }
+            }
+
+        Some(near)
+        }
+
+    // Möller–Trumbore, both sides of the triangle are hit, returns the distance and the barycentric weights
+    pub fn intersect_triangle(&self, a: Point3<f32>, b: Point3<f32>, c: Point3<f32>) -> Option<(f32, Vec3<f32>)> {
+        let edge_ab = b - a;
+        let edge_ac = c - a;
+
+        let p = self.direction.cross(edge_ac);
+        let determinant = edge_ab.dot(p);
+        // Ray runs parallel to the triangle
+        if determinant.abs() < EPSILON {
+            return None;
+            }
+
+        let inverse = 1.0 / determinant;
+        let offset = self.origin - a;
+
+        let u = offset.dot(p) * inverse;
+        if ! (0.0 ..= 1.0).contains(&u) {
+            return None;
+            }
+
+        let q = offset.cross(edge_ab);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+            }
+
+        let distance = edge_ac.dot(q) * inverse;
+        match distance > EPSILON {
+            true => Some((distance, [1.0 - u - v, u, v])),
+            false => None
+            }
+        }
+    }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{a} is not {b}");
+        }
+
+    #[test]
+    fn rays_enter_boxes_at_the_near_slab() {
+        let (min, max) = (Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vector3::unit_x());
+        assert_near(ray.intersect_aabb(min, max).unwrap(), 4.0);
+
+        let diagonal = Ray::new(Point3::new(-3.0, -3.0, 0.0), Vector3::new(1.0, 1.0, 0.0));
+        assert_near(diagonal.intersect_aabb(min, max).unwrap(), 2.0_f32.sqrt() * 2.0);
+
+        let inside = Ray::new(Point3::origin(), Vector3::unit_z());
+        assert_eq!(inside.intersect_aabb(min, max), Some(0.0));
+        }
+
+    #[test]
+    fn rays_miss_boxes_beside_and_behind_them() {
+        let (min, max) = (Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+
+        // Parallel to the x slabs, but outside of them
+        let beside = Ray::new(Point3::new(-5.0, 2.0, 0.0), Vector3::unit_x());
+        assert_eq!(beside.intersect_aabb(min, max), None);
+
+        let behind = Ray::new(Point3::new(5.0, 0.0, 0.0), Vector3::unit_x());
+        assert_eq!(behind.intersect_aabb(min, max), None);
+        }
+
+    #[test]
+    fn rays_miss_inverted_boxes() {
+        let min = Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let max = Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+
+        let ray = Ray::new(Point3::origin(), Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(ray.intersect_aabb(min, max), None);
+        }
+
+    #[test]
+    fn rays_hit_both_sides_of_triangles() {
+        let [a, b, c] = [Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)];
+
+        let front = Ray::new(Point3::new(0.25, 0.25, 2.0), - Vector3::unit_z());
+        let (distance, [wa, wb, wc]) = front.intersect_triangle(a, b, c).unwrap();
+        assert_near(distance, 2.0);
+        assert_near(wa, 0.5);
+        assert_near(wb, 0.25);
+        assert_near(wc, 0.25);
+
+        let back = Ray::new(Point3::new(0.25, 0.25, -1.0), Vector3::unit_z());
+        assert_near(back.intersect_triangle(a, b, c).unwrap().0, 1.0);
+        }
+
+    #[test]
+    fn rays_miss_triangles_outside_parallel_and_behind() {
+        let [a, b, c] = [Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)];
+
+        let outside = Ray::new(Point3::new(0.75, 0.75, 1.0), - Vector3::unit_z());
+        assert_eq!(outside.intersect_triangle(a, b, c), None);
+
+        let parallel = Ray::new(Point3::new(-1.0, 0.25, 0.0), Vector3::unit_x());
+        assert_eq!(parallel.intersect_triangle(a, b, c), None);
+
+        let away = Ray::new(Point3::new(0.25, 0.25, 1.0), Vector3::unit_z());
+        assert_eq!(away.intersect_triangle(a, b, c), None);
+
+        // Rays leaving the surface don't hit it again
+        let on_surface = Ray::new(Point3::new(0.25, 0.25, 0.0), Vector3::unit_z());
+        assert_eq!(on_surface.intersect_triangle(a, b, c), None);
+        }
+
+    #[test]
+    fn local_rays_undo_the_instance_transform() {
+        let rotation = Quaternion::from_angle_y(Deg(90.0));
+        let ray = Ray::new(Point3::new(5.0, 0.0, 0.0), Vector3::unit_x())
+            .to_local(Vector3::new(5.0, 0.0, 0.0), rotation);
+
+        assert!(ray.get_origin().to_vec().magnitude() < 1e-5);
+        assert!((ray.get_direction() - Vector3::unit_z()).magnitude() < 1e-5);
+        }
+    }
@@ -0,0 +1,554 @@
+use {
+    anyhow::{
+        anyhow,
+        Context,
+        Result as DynResult
+        },
+    bytemuck::cast_slice,
+    cgmath::{
+        Deg,
+        InnerSpace,
+        Quaternion,
+        Rotation3,
+        Vector3,
+        Zero
+        },
+    ron::ser::PrettyConfig,
+    serde::{
+        Deserialize,
+        Serialize
+        },
+    wgpu::{
+        *,
+        util::*
+        },
+    std::{
+        fs,
+        ops::Range,
+        path::{
+            Path,
+            PathBuf
+            }
+        },
+    crate::{
+        assets::{
+            Assets,
+            Handle,
+            TextureAsset
+            },
+        camera::Camera,
+        culling::get_draw_offset,
+        instance::{
+            Instance as ModelInstance,
+            InstanceRaw
+            },
+        mesh::{
+            Mesh,
+            MeshData
+            },
+        particles::ParticleEmitter,
+        raycast::{
+            Ray,
+            RayHit
+            },
+        vertex::Vertex,
+        utils::*
+        }
+    };
+
+// Serializable description of everything that is placed in the scene
+#[derive(Serialize, Deserialize)]
+pub struct SceneDescription {
+    pub meshes: Vec<MeshDescription>,
+    pub textures: Vec<TextureDescription>,
+    pub instances: Vec<InstanceDescription>,
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emitters: Vec<ParticleEmitter>
+    }
+
+#[derive(Serialize, Deserialize)]
+pub struct MeshDescription {
+    pub name: String,
+    // Mesh file relative to the assets directory, or pack, the inline geometry is used when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vertices: Vec<Vertex>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indices: Vec<u16>
+    }
+
+#[derive(Serialize, Deserialize)]
+pub struct TextureDescription {
+    pub name: String,
+    // Relative to the assets directory, or pack
+    pub path: PathBuf
+    }
+
+#[derive(Serialize, Deserialize)]
+pub struct InstanceDescription {
+    pub mesh: String,
+    pub texture: String,
+    pub position: Vec3<f32>,
+    // Quaternion stored in the (w, x, y, z) order
+    pub rotation: Vec4<f32>
+    }
+
+#[derive(Serialize, Deserialize)]
+pub struct CameraDescription {
+    pub eye: Vec3<f32>,
+    pub target: Vec3<f32>,
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32
+    }
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct LightDescription {
+    pub position: Vec3<f32>,
+    pub color: Vec3<f32>,
+    pub intensity: f32
+    }
+
+impl SceneDescription {
+    pub fn load(path: &Path) -> DynResult<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read scene file {}", path.display()))?;
+
+        ron::from_str(&text)
+            .with_context(|| format!("Unable to parse scene file {}", path.display()))
+        }
+
+    pub fn save(&self, path: &Path) -> DynResult<()> {
+        let text = ron::ser::to_string_pretty(self, PrettyConfig::default())?;
+
+        fs::write(path, text)
+            .with_context(|| format!("Unable to write scene file {}", path.display()))
+        }
+
+    pub fn create_camera(&self, aspect: f32) -> Camera {
+        let CameraDescription { eye, target, fovy, znear, zfar } = self.camera;
+        Camera::new(eye, target, aspect, fovy, znear, zfar)
+        }
+    }
+
+// The scene which was previously hardcoded inside of the State
+impl Default for SceneDescription {
+    fn default() -> Self {
+        const NUM_INSTANCE_PER_ROW: u32 = 8;
+        const INSTANCE_DISPLACEMENT: Vector3<f32> = Vector3::new(
+            NUM_INSTANCE_PER_ROW as f32 * 0.5,
+            0.0,
+            NUM_INSTANCE_PER_ROW as f32 * 0.5
+            );
+
+        let instances = (0 .. NUM_INSTANCE_PER_ROW * NUM_INSTANCE_PER_ROW)
+            .map(|i| {
+                let (x, z) = (
+                    i % NUM_INSTANCE_PER_ROW,
+                    i / NUM_INSTANCE_PER_ROW
+                    );
+
+                let position = Vector3::new(x as f32, 0.0, z as f32) - INSTANCE_DISPLACEMENT;
+
+                // Check at Zero point as Quaternions can affect scale, if not used properly
+                let rotation = match position.is_zero() {
+                    true => Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0)),
+                    false => Quaternion::from_axis_angle(position.normalize(), Deg(45.0))
+                    };
+
+                InstanceDescription::from_instance("pentagon", "happy-tree", &ModelInstance::new(position, rotation))
+                })
+            .collect();
+
+        Self {
+            meshes: vec![
+                MeshDescription {
+                    name: "pentagon".to_owned(),
+                    path: None,
+                    vertices: vec![
+                        Vertex::new([-0.0868241,   0.49240386, 0.0], [0.4131759,    0.00759614]),
+                        Vertex::new([-0.49513406,  0.06958647, 0.0], [0.0048659444, 0.43041354]),
+                        Vertex::new([-0.21918549, -0.44939706, 0.0], [0.28081453,   0.949397]),
+                        Vertex::new([ 0.35966998, -0.3473291,  0.0], [0.85967,      0.84732914]),
+                        Vertex::new([ 0.44147372,  0.2347359,  0.0], [0.9414737,    0.2652641])
+                        ],
+                    indices: vec![
+                        0, 1, 4,
+                        1, 2, 4,
+                        2, 3, 4
+                        ]
+                    }
+                ],
+            textures: vec![
+                TextureDescription {
+                    name: "happy-tree".to_owned(),
+                    path: PathBuf::from("happy-tree.png")
+                    }
+                ],
+            instances,
+            camera: CameraDescription {
+                eye: [0.0, 1.0, 2.0],
+                target: [0.0; 3],
+                fovy: 45.0,
+                znear: 0.1,
+                zfar: 100.0
+                },
+            lights: vec![
+                LightDescription {
+                    position: [2.0, 2.0, 2.0],
+                    color: [1.0; 3],
+                    intensity: 1.0
+                    }
+                ],
+            emitters: Vec::new()
+            }
+        }
+    }
+
+impl InstanceDescription {
+    pub fn from_instance(mesh: &str, texture: &str, instance: &ModelInstance) -> Self {
+        let position = instance.get_position();
+        let Quaternion { v, s } = instance.get_rotation();
+
+        Self {
+            mesh: mesh.to_owned(),
+            texture: texture.to_owned(),
+            position: position.into(),
+            rotation: [s, v.x, v.y, v.z]
+            }
+        }
+
+    pub fn to_instance(&self) -> ModelInstance {
+        let [w, x, y, z] = self.rotation;
+        ModelInstance::new(self.position.into(), Quaternion::new(w, x, y, z))
+        }
+    }
+
+impl From<&Camera> for CameraDescription {
+    fn from(camera: &Camera) -> Self {
+        Self {
+            eye: camera.get_eye().into(),
+            target: camera.get_target().into(),
+            fovy: camera.get_fovy(),
+            znear: camera.get_znear(),
+            zfar: camera.get_zfar()
+            }
+        }
+    }
+
+pub struct SceneMesh {
+    name: String,
+    // Meshes without a path are stored inline in the scene file
+    path: Option<PathBuf>,
+    handle: Handle<Mesh>
+    }
+
+pub struct SceneTexture {
+    name: String,
+    path: PathBuf,
+    handle: Handle<TextureAsset>
+    }
+
+// Range of instances inside of the instance buffer, which share the same mesh and texture
+struct Batch {
+    mesh: usize,
+    texture: usize,
+    instances: Range<u32>
+    }
+
+// GPU side of the scene description
+pub struct Scene {
+    meshes: Vec<SceneMesh>,
+    textures: Vec<SceneTexture>,
+    instances: Vec<ModelInstance>,
+    // Position of every instance of the buffer inside of the scene file
+    file_indices: Vec<usize>,
+    batches: Vec<Batch>,
+    instance_buffer: Buffer,
+    lights: Vec<LightDescription>,
+    emitters: Vec<ParticleEmitter>
+    }
+
+impl SceneMesh {
+    pub fn get_name(&self) -> &str {
+        &self.name
+        }
+
+    pub fn get_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+        }
+
+    pub const fn get_handle(&self) -> &Handle<Mesh> {
+        &self.handle
+        }
+    }
+
+impl SceneTexture {
+    pub fn get_name(&self) -> &str {
+        &self.name
+        }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+        }
+
+    pub const fn get_handle(&self) -> &Handle<TextureAsset> {
+        &self.handle
+        }
+    }
+
+impl Scene {
+    // Files are loaded in the background
+    pub fn new(device: &Device, assets: &mut Assets, description: &SceneDescription) -> DynResult<Self> {
+        let meshes: Vec<_> = description.meshes.iter()
+            .map(|MeshDescription { name, path, vertices, indices }| SceneMesh {
+                name: name.clone(),
+                path: path.clone(),
+                handle: match path {
+                    Some(path) => assets.load_mesh(path),
+                    None => assets.add_mesh(name, MeshData {
+                        vertices: vertices.clone(),
+                        indices: indices.clone()
+                        })
+                    }
+                })
+            .collect();
+
+        let textures: Vec<_> = description.textures.iter()
+            .map(|TextureDescription { name, path }| SceneTexture {
+                name: name.clone(),
+                path: path.clone(),
+                handle: assets.load_texture(path)
+                })
+            .collect();
+
+        for (index, emitter) in description.emitters.iter().enumerate() {
+            emitter.validate()
+                .with_context(|| format!("Invalid particle emitter {index}"))?;
+            }
+
+        let mut resolved = description.instances.iter()
+            .enumerate()
+            .map(|(index, instance)| {
+                let mesh = meshes.iter()
+                    .position(|mesh| mesh.name == instance.mesh)
+                    .ok_or_else(|| anyhow!("Unknown mesh {}", instance.mesh))?;
+                let texture = textures.iter()
+                    .position(|texture| texture.name == instance.texture)
+                    .ok_or_else(|| anyhow!("Unknown texture {}", instance.texture))?;
+
+                Ok((mesh, texture, index, instance.to_instance()))
+                })
+            .collect::<DynResult<Vec<_>>>()?;
+
+        // Instances sharing both mesh and texture have to lie next to each other, so they can be drawn with a single call
+        resolved.sort_by_key(|&(mesh, texture, ..)| (mesh, texture));
+
+        let mut batches: Vec<Batch> = Vec::new();
+        for (i, &(mesh, texture, ..)) in resolved.iter().enumerate() {
+            let i = i as u32;
+            match batches.last_mut() {
+                Some(batch) if batch.mesh == mesh && batch.texture == texture =>
+                    batch.instances.end = i + 1,
+                _ => batches.push(Batch { mesh, texture, instances: i .. i + 1 })
+                };
+            }
+
+        let (file_indices, instances): (Vec<_>, Vec<_>) = resolved.into_iter()
+            .map(|(_, _, index, instance)| (index, instance))
+            .unzip();
+
+        let instances_data: Vec<_> = instances.iter()
+            .map(ModelInstance::to_raw)
+            .collect();
+
+        let instance_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Instance Buffer"),
+            contents: cast_slice(&instances_data),
+            usage: BufferUsages::VERTEX
+            });
+
+        Ok(Self {
+            meshes,
+            textures,
+            instances,
+            file_indices,
+            batches,
+            instance_buffer,
+            lights: description.lights.clone(),
+            emitters: description.emitters.clone()
+            })
+        }
+
+    // Writes back the current runtime state, instances keep their order from the loaded file
+    pub fn to_description(&self, camera: &Camera, assets: &Assets) -> SceneDescription {
+        let mut instances: Vec<_> = self.batches.iter()
+            .flat_map(|batch| {
+                let Range { start, end } = batch.instances.clone();
+                let mesh = &self.meshes[batch.mesh].name;
+                let texture = &self.textures[batch.texture].name;
+
+                (start as usize .. end as usize)
+                    .map(move |slot| (self.file_indices[slot], InstanceDescription::from_instance(mesh, texture, &self.instances[slot])))
+                })
+            .collect();
+        instances.sort_by_key(|&(index, _)| index);
+
+        SceneDescription {
+            meshes: self.meshes.iter()
+                .map(|mesh| {
+                    let MeshData { vertices, indices } = match mesh.path {
+                        Some(_) => MeshData::default(),
+                        None => assets.get_mesh(&mesh.handle)
+                            .get()
+                            .get_data()
+                            .clone()
+                        };
+
+                    MeshDescription {
+                        name: mesh.name.clone(),
+                        path: mesh.path.clone(),
+                        vertices,
+                        indices
+                        }
+                    })
+                .collect(),
+            textures: self.textures.iter()
+                .map(|texture| TextureDescription {
+                    name: texture.name.clone(),
+                    path: texture.path.clone()
+                    })
+                .collect(),
+            instances: instances.into_iter()
+                .map(|(_, instance)| instance)
+                .collect(),
+            camera: camera.into(),
+            lights: self.lights.clone(),
+            emitters: self.emitters.clone()
+            }
+        }
+
+    pub fn get_meshes(&self) -> &[SceneMesh] {
+        &self.meshes
+        }
+
+    pub fn get_textures(&self) -> &[SceneTexture] {
+        &self.textures
+        }
+
+    // Ordered like the instance buffer, which groups them by batch
+    pub fn get_instances(&self) -> &[ModelInstance] {
+        &self.instances
+        }
+
+    // Position in the scene file of the instance at the given slot of the instance buffer, None for an unknown slot
+    pub fn get_file_index(&self, slot: usize) -> Option<usize> {
+        self.file_indices.get(slot).copied()
+        }
+
+    pub fn get_lights(&self) -> &[LightDescription] {
+        &self.lights
+        }
+
+    pub fn get_emitters(&self) -> &[ParticleEmitter] {
+        &self.emitters
+        }
+
+    // Mesh, and the range of instances of every draw call
+    pub fn get_batches(&self) -> impl Iterator<Item = (&SceneMesh, Range<u32>)> {
+        self.batches.iter()
+            .map(|batch| (&self.meshes[batch.mesh], batch.instances.clone()))
+        }
+
+    // Nearest instance along the ray, meshes which are still loading are tested as placeholders
+    pub fn raycast(&self, ray: &Ray, assets: &Assets) -> Option<RayHit> {
+        let mut nearest: Option<RayHit> = None;
+
+        for Batch { mesh, instances, .. } in &self.batches {
+            let mesh = assets.get_mesh(&self.meshes[*mesh].handle).get();
+            let (min, max) = mesh.get_bounds();
+            let MeshData { vertices, indices } = mesh.get_data();
+            // Placeholders of meshes which are still loading have nothing to hit
+            if indices.is_empty() {
+                continue;
+                }
+
+            for instance in instances.start as usize .. instances.end as usize {
+                let transform = &self.instances[instance];
+                // Instances have no scale, so the distances stay the same in local space
+                let local = ray.to_local(transform.get_position(), transform.get_rotation());
+
+                // Boxes further than the current hit can't contain a closer one
+                match local.intersect_aabb(min, max) {
+                    Some(distance) if nearest.is_none_or(|hit| distance < hit.distance) => (),
+                    _ => continue
+                    };
+
+                for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+                    let [a, b, c] = [0, 1, 2].map(|i| array_to_point3(vertices[corners[i] as usize].get_position()));
+
+                    if let Some((distance, barycentric)) = local.intersect_triangle(a, b, c)
+                        && nearest.is_none_or(|hit| distance < hit.distance) {
+                        nearest = Some(RayHit {
+                            distance,
+                            position: ray.at(distance),
+                            instance: self.file_indices[instance],
+                            triangle: triangle * 3,
+                            barycentric
+                            });
+                        }
+                    }
+                }
+            }
+
+        nearest
+        }
+
+    // Leaves the bind groups to the caller, for pipelines which don't use the textures
+    pub fn draw_meshes(&self, render_pass: &mut RenderPass, assets: &Assets, mut draw: impl FnMut(&mut RenderPass, &Mesh, Range<u32>)) {
+        if self.instances.is_empty() {
+            return;
+            }
+
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+
+        for Batch { mesh, instances, .. } in &self.batches {
+            draw(render_pass, assets.get_mesh(&self.meshes[*mesh].handle).get(), instances.clone());
+            }
+        }
+
+    // Draws with the counts written by GpuCulling, every batch reads the instances from its own range of the buffer
+    pub fn draw_indirect(&self, render_pass: &mut RenderPass, assets: &Assets, instance_buffer: &Buffer, indirect_buffer: &Buffer) {
+        let instance_size = size_of::<InstanceRaw>() as BufferAddress;
+
+        for (index, Batch { mesh, texture, instances }) in self.batches.iter().enumerate() {
+            let texture = assets.get_texture(&self.textures[*texture].handle);
+            let mesh = assets.get_mesh(&self.meshes[*mesh].handle);
+
+            render_pass.set_bind_group(0, texture.get().get_bind_group(), &[]);
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(instances.start as BufferAddress * instance_size .. instances.end as BufferAddress * instance_size));
+            mesh.get().draw_indirect(render_pass, indirect_buffer, get_draw_offset(index));
+            }
+        }
+
+    pub fn draw(&self, render_pass: &mut RenderPass, assets: &Assets) {
+        // Empty buffers can not be bound
+        if self.instances.is_empty() {
+            return;
+            }
+
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+
+        for Batch { mesh, texture, instances } in &self.batches {
+            let texture = assets.get_texture(&self.textures[*texture].handle);
+            let mesh = assets.get_mesh(&self.meshes[*mesh].handle);
+
+            render_pass.set_bind_group(0, texture.get().get_bind_group(), &[]);
+            mesh.get().draw(render_pass, instances.clone());
+            }
+        }
+    }
\ No newline at end of file
@@ -0,0 +1,116 @@
+use {
+    anyhow::{
+        anyhow,
+        Result as DynResult
+        },
+    image::RgbaImage,
+    log::*,
+    wgpu::{
+        *,
+        Texture as WGPUTexture
+        },
+    std::{
+        path::PathBuf,
+        sync::mpsc::channel,
+        thread
+        }
+    };
+
+// Copy of a rendered frame, which waits for the GPU to finish before it can be saved
+pub struct Screenshot {
+    buffer: Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    format: TextureFormat,
+    path: PathBuf
+    }
+
+impl Screenshot {
+    // Records the copy into the encoder, the texture has to be created with TextureUsages::COPY_SRC
+    pub fn new(device: &Device, encoder: &mut CommandEncoder, texture: &WGPUTexture, path: PathBuf) -> Self {
+        let Extent3d { width, height, .. } = texture.size();
+
+        // Rows of the copied texture must be aligned inside of the buffer
+        let padded_bytes_per_row = (4 * width).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Screenshot Buffer"),
+            size: (padded_bytes_per_row * height) as BufferAddress,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false
+            });
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height)
+                    }
+                },
+            texture.size()
+            );
+
+        Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            format: texture.format(),
+            path
+            }
+        }
+
+    // Waiting for the GPU happens on a separate thread, so the event loop is not blocked
+    pub fn save_in_background(self, device: Device) {
+        thread::spawn(move || {
+            let path = self.path.clone();
+            match self.save(&device) {
+                Ok(_) => info!("Saved the screenshot to {}", path.display()),
+                Err(e) => error!("Unable to save the screenshot {e:#}")
+                };
+            });
+        }
+
+    fn save(self, device: &Device) -> DynResult<()> {
+        let slice = self.buffer.slice(..);
+
+        let (sender, receiver) = channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+            });
+
+        device.poll(PollType::Wait)?;
+        receiver.recv()??;
+
+        let swap_channels = match self.format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            format => return Err(anyhow!("Unsupported surface format {format:?}"))
+            };
+
+        let mut pixels = Vec::with_capacity((4 * self.width * self.height) as usize);
+        /* The mapped view needs to be dropped before unmapping */ {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[.. (4 * self.width) as usize]);
+                }
+            }
+        self.buffer.unmap();
+
+        if swap_channels {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+                }
+            }
+
+        RgbaImage::from_raw(self.width, self.height, pixels)
+            .ok_or_else(|| anyhow!("Screenshot buffer has an invalid size"))?
+            .save(&self.path)?;
+
+        Ok(())
+        }
+    }
\ No newline at end of file
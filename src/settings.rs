@@ -0,0 +1,105 @@
+use {
+    anyhow::{
+        Context,
+        Result as DynResult
+        },
+    log::*,
+    serde::{
+        Deserialize,
+        Serialize
+        },
+    wgpu::PresentMode,
+    std::{
+        fs,
+        path::Path
+        },
+    crate::stats::StatsOutput
+    };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RedrawMode {
+    // Draws frames one after another
+    #[default]
+    Continuous,
+    // Draws only after input, events, or while the camera is animating
+    OnDemand
+    }
+
+// User configuration, every missing field falls back to its default
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub title: String,
+    // Window sizes are in logical pixels, so they get multiplied by the display's scale factor
+    pub width: u32,
+    pub height: u32,
+    // The window can't be shrunk below this size
+    pub min_size: Option<(u32, u32)>,
+    pub is_resizable: bool,
+    // Renders at this fixed size, scaled up to the window by whole multiples
+    pub virtual_resolution: Option<(u32, u32)>,
+    // Multiplier of the window's physical size used for rendering, ignored with a virtual resolution
+    pub render_scale: f32,
+    // Falls back to Fifo when the surface doesn't support it
+    pub present_mode: PresentMode,
+    // Maximum frames per second, unlimited when missing
+    pub frame_cap: Option<u32>,
+    pub redraw_mode: RedrawMode,
+    // Rendering always stops while the window is hidden
+    pub is_paused_when_unfocused: bool,
+    // Simulation steps per second, independent of the frame rate
+    pub update_rate: u32,
+    // Steps run in a single frame when catching up, the rest of the lag is dropped
+    pub max_update_steps: u32,
+    // Seconds between frame time reports, none are made when missing
+    pub stats_interval: Option<f32>,
+    pub stats_output: StatsOutput,
+    // Adds GPU times of the render passes to the stats, when the adapter supports timestamp queries
+    pub is_gpu_profiling: bool
+    }
+
+impl Settings {
+    pub fn load(path: &Path) -> DynResult<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read settings {}", path.display()))?;
+
+        ron::from_str(&text)
+            .with_context(|| format!("Unable to parse settings {}", path.display()))
+        }
+
+    // A missing file is not an error, as all of the settings are optional
+    pub fn load_or_default(path: &Path) -> Self {
+        if ! path.exists() {
+            return Self::default();
+            }
+
+        Self::load(path)
+            .unwrap_or_else(|e| {
+                warn!("Using default settings {e:#}");
+                Self::default()
+                })
+        }
+    }
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            title: "WGPU Practice".to_owned(),
+            width: 320,
+            height: 180,
+            min_size: Some((160, 90)),
+            is_resizable: true,
+            virtual_resolution: None,
+            render_scale: 1.0,
+            present_mode: PresentMode::Fifo,
+            frame_cap: None,
+            redraw_mode: RedrawMode::Continuous,
+            is_paused_when_unfocused: true,
+            update_rate: 60,
+            max_update_steps: 5,
+            stats_interval: Some(1.0),
+            stats_output: StatsOutput::Title,
+            is_gpu_profiling: false
+            }
+        }
+    }
\ No newline at end of file
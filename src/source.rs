@@ -0,0 +1,69 @@
+use {
+    anyhow::{
+        Context,
+        Result as DynResult
+        },
+    log::*,
+    std::{
+        env::current_exe,
+        fs,
+        path::{
+            Path,
+            PathBuf
+            }
+        },
+    crate::pack::Pack
+    };
+
+const ASSETS_DIR: &str = "assets";
+const ASSETS_PACK: &str = "assets.pack";
+
+// Where the asset paths are resolved from
+pub enum AssetSource {
+    Directory(PathBuf),
+    Pack(Pack)
+    }
+
+impl AssetSource {
+    // Looks next to the executable first, then falls back to the working directory for development
+    pub fn locate() -> DynResult<Self> {
+        let exe_dir = current_exe()?
+            .parent()
+            .map(Path::to_owned)
+            .unwrap_or_default();
+
+        let pack_path = exe_dir.join(ASSETS_PACK);
+        if pack_path.is_file() {
+            info!("Loading assets from {}", pack_path.display());
+            return Ok(Self::Pack(Pack::open(&pack_path)?));
+            }
+
+        let dir = match exe_dir.join(ASSETS_DIR) {
+            dir if dir.is_dir() => dir,
+            _ => PathBuf::from(ASSETS_DIR)
+            };
+
+        info!("Loading assets from {}", dir.display());
+        Ok(Self::Directory(dir))
+        }
+
+    pub fn read(&self, path: &Path) -> DynResult<Vec<u8>> {
+        match self {
+            Self::Directory(dir) => fs::read(dir.join(path))
+                .with_context(|| format!("Unable to read asset {}", path.display())),
+            Self::Pack(pack) => pack.read(path)
+            }
+        }
+
+    pub fn read_to_string(&self, path: &Path) -> DynResult<String> {
+        Ok(String::from_utf8(self.read(path)?)?)
+        }
+
+    // Only directories can change while running
+    pub fn get_directory(&self) -> Option<&Path> {
+        match self {
+            Self::Directory(dir) => Some(dir),
+            Self::Pack(_) => None
+            }
+        }
+    }
\ No newline at end of file
@@ -2,12 +2,8 @@ use {
     anyhow::Result as DynResult,
     bytemuck::cast_slice,
     cgmath::{
-        Deg,
-        Vector3,
-        Quaternion,
-        InnerSpace,
-        Rotation3,
-        Zero
+        EuclideanSpace,
+        Point3
         },
     log::*,
     wgpu::{
@@ -15,71 +11,165 @@ use {
         util::*
         },
     winit::{
-        dpi::PhysicalSize,
-        event_loop::ActiveEventLoop,
+        dpi::{
+            LogicalSize,
+            PhysicalPosition,
+            PhysicalSize
+            },
+        event_loop::{
+            ActiveEventLoop,
+            ControlFlow,
+            EventLoopProxy
+            },
         keyboard::KeyCode,
         // platform::windows::WindowExtWindows,
         window::*
         },
     std::{
+        any::Any,
         iter::once,
-        sync::Arc
+        path::{
+            Path,
+            PathBuf
+            },
+        sync::Arc,
+        thread,
+        time::{
+            Duration,
+            Instant,
+            SystemTime,
+            UNIX_EPOCH
+            }
         },
     crate::{
+        app::AppEvent,
+        assets::Assets,
         camera::*,
-        instance::{
-            Instance as ModelInstance,
-            InstanceRaw
+        cinematic::*,
+        controller::*,
+        culling::GpuCulling,
+        debug_draw::DebugDraw,
+        debug_view::*,
+        input::*,
+        instance::InstanceRaw,
+        light::LightUniform,
+        particles::{
+            ParticleBackend,
+            ParticleSystem
             },
+        picking::Picker,
+        profiler::GpuProfiler,
+        raycast::RayHit,
+        scene::*,
+        screenshot::Screenshot,
+        settings::*,
+        source::AssetSource,
+        stats::*,
+        text::TextRenderer,
         texture::Texture,
+        upscale::Upscaler,
         vertex::Vertex,
-        utils::VertexInfo
+        utils::{
+            Vec2,
+            VertexInfo
+            }
         }
     };
 
-const VERTICES: &[Vertex] = &[
-    Vertex::new([-0.0868241,   0.49240386, 0.0], [0.4131759,    0.00759614]),
-    Vertex::new([-0.49513406,  0.06958647, 0.0], [0.0048659444, 0.43041354]),
-    Vertex::new([-0.21918549, -0.44939706, 0.0], [0.28081453,   0.949397]),
-    Vertex::new([ 0.35966998, -0.3473291,  0.0], [0.85967,      0.84732914]),
-    Vertex::new([ 0.44147372,  0.2347359,  0.0], [0.9414737,    0.2652641]),
-    ];
+// Limits of the render scale, as huge targets could exhaust the memory
+const MIN_RENDER_SCALE: f32 = 0.25;
+const MAX_RENDER_SCALE: f32 = 4.0;
+
+// Frame times kept for the rolling statistics
+const STATS_SAMPLES: usize = 240;
 
-const INDICES: &[u16] = &[
-    0, 1, 4,
-    1, 2, 4,
-    2, 3, 4,
+// Order in which Action::NextPresentMode goes through the modes
+const PRESENT_MODES: [PresentMode; 4] = [
+    PresentMode::Fifo,
+    PresentMode::Mailbox,
+    PresentMode::Immediate,
+    PresentMode::AutoVsync
     ];
 
 // Store the game state
 pub struct State {
     window: Arc<Window>,
+    proxy: EventLoopProxy<AppEvent>,
     surface: Surface<'static>,
     device: Device,
     queue: Queue,
     config: SurfaceConfiguration,
+    present_modes: Vec<PresentMode>,
     render_pipeline: RenderPipeline,
-    vertex_buffer: Buffer,
-    index_buffer: Buffer,
-    num_indices: u32,
-    diffuse_bind_group: BindGroup,
-    diffuse_texture: Texture,
+    assets: Assets,
+    scene: Scene,
+    scene_path: Option<PathBuf>,
+    // Missing without compute shaders, or indirect draws, the scene is then drawn directly
+    culling: Option<GpuCulling>,
     depth_texture: Texture,
+    // Only present with a fixed virtual resolution
+    upscaler: Option<Upscaler>,
+    virtual_resolution: Option<(u32, u32)>,
+    render_scale: f32,
+    scale_factor: f64,
     camera: Camera,
+    // Camera from before the last simulation step, rendering blends between the two
+    previous_camera: Camera,
     camera_uniform: CameraUniform,
     camera_buffer: Buffer,
     camera_bind_group: BindGroup,
-    camera_controller: CameraController,
-    instances: Vec<ModelInstance>,
-    instance_buffer: Buffer,
+    camera_controllers: Vec<Box<dyn CameraController>>,
+    active_controller: usize,
+    cinematic: Cinematic,
+    last_update: Instant,
+    light_buffer: Buffer,
+    light_bind_group: BindGroup,
+    timestep: Duration,
+    max_update_steps: u32,
+    // Time not yet simulated
+    accumulator: Duration,
+    redraw_mode: RedrawMode,
+    // Shortest time between the frames, from the frame cap
+    min_frame_time: Option<Duration>,
+    last_frame: Instant,
+    is_redraw_requested: bool,
+    is_paused_when_unfocused: bool,
+    is_focused: bool,
+    is_occluded: bool,
+    key_bindings: KeyBindings,
+    stats: Option<FrameStats>,
+    stats_output: StatsOutput,
+    profiler: Option<GpuProfiler>,
+    text: TextRenderer,
+    debug_draw: DebugDraw,
+    // Shows the built-in gizmos, shapes added through get_debug_draw_mut are always drawn
+    is_debug_drawing: bool,
+    debug_view: DebugView,
+    debug_view_renderer: DebugViewRenderer,
+    particles: ParticleSystem,
+    picker: Picker,
+    cursor_position: Option<PhysicalPosition<f64>>,
+    // Pixel of the render target to read the instance from during the next render
+    pending_pick: Option<(u32, u32)>,
+    // Slot of the instance buffer, as drawn by the highlight
+    selected_instance: Option<usize>,
+    // Increased with every replaced scene, so picks from an older one can be dropped
+    scene_generation: u64,
+    // Latest report, for StatsOutput::Overlay
+    stats_text: String,
+    // Frame stats get appended to it
+    title: String,
+    pending_screenshot: Option<PathBuf>,
     is_surface_configured: bool
     }
 
 impl State {
-    pub async fn new(window: Arc<Window>) -> DynResult<Self> {
-        window.set_title("WGPU Practice");
-        window.set_resizable(false);
-        match window.request_inner_size(PhysicalSize { width: 320, height: 180}) {
+    // Without a scene file the built-in scene is loaded
+    pub async fn new(window: Arc<Window>, proxy: EventLoopProxy<AppEvent>, settings: Settings, scene_path: Option<PathBuf>, key_bindings: KeyBindings) -> DynResult<Self> {
+        window.set_title(&settings.title);
+        window.set_resizable(settings.is_resizable);
+        window.set_min_inner_size(settings.min_size.map(|(width, height)| LogicalSize { width, height }));
+        match window.request_inner_size(LogicalSize { width: settings.width, height: settings.height }) {
             Some(PhysicalSize { width, height }) => info!("Set initial size of: {width}x{height}"),
             _ => info!("Unable to set size")
             }
@@ -87,6 +177,7 @@ impl State {
         // window.set_taskbar_icon(taskbar_icon);
 
         let size = window.inner_size();
+        let scale_factor = window.scale_factor();
 
         let instance = Instance::new(&InstanceDescriptor {
             backends: Backends::PRIMARY,
@@ -104,7 +195,11 @@ impl State {
 
         let (device, queue) = adapter.request_device(&DeviceDescriptor {
             label: Some("Device Descriptor"),
-            required_features: Features::empty(),
+            // Only requested when supported, so that optional features can't prevent startup
+            required_features: adapter.features() & match settings.is_gpu_profiling {
+                true => Features::TIMESTAMP_QUERY | Features::POLYGON_MODE_LINE,
+                false => Features::POLYGON_MODE_LINE
+                },
             required_limits: Limits::default(),
             memory_hints: Default::default(),
             trace: Trace::Off
@@ -118,24 +213,31 @@ impl State {
             .copied()
             .unwrap_or(surface_caps.formats[0]);
 
+        // Copying out of the surface is needed for screenshots, but not every platform allows it
+        let usage = match surface_caps.usages.contains(TextureUsages::COPY_SRC) {
+            true => TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            false => TextureUsages::RENDER_ATTACHMENT
+            };
+
+        let present_mode = match is_present_mode_supported(&surface_caps.present_modes, settings.present_mode) {
+            true => settings.present_mode,
+            false => {
+                warn!("Present mode {:?} is not supported, using {:?}", settings.present_mode, PresentMode::Fifo);
+                PresentMode::Fifo
+                }
+            };
+
         let config = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: surface_caps.present_modes[0],
+            present_mode,
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2
             };
 
-        let diffuse_texture = Texture::from_bytes(
-            &device,
-            &queue,
-            include_bytes!("../assets/happy-tree.png"),
-            Some("Happy Tree")
-            )?;
-
         let texture_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("Texture Bind Group Layout"),
             entries: &[
@@ -159,31 +261,33 @@ impl State {
                 ]
             });
 
-        let diffuse_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Diffuse Bind Group"),
-            layout: &texture_bind_group_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(diffuse_texture.get_view())
-                    },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Sampler(diffuse_texture.get_sampler())
-                    }
-                ]
-            });
+        let scene_description = match &scene_path {
+            Some(path) => SceneDescription::load(path)?,
+            None => SceneDescription::default()
+            };
+
+        let mut assets = Assets::new(&device, &queue, &texture_bind_group_layout, AssetSource::locate()?, proxy.clone())?;
+
+        let scene = Scene::new(&device, &mut assets, &scene_description)?;
+
+        let depth_texture = Texture::create_depth_texture(&device, config.width, config.height, Some("Depth Texture"));
+
+        let render_scale = settings.render_scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE);
+        let text = TextRenderer::new(&device, &queue, config.format);
 
-        let depth_texture = Texture::create_depth_texture(&device, &config, Some("Depth Texture"));
+        let profiler = match settings.is_gpu_profiling {
+            true => GpuProfiler::new(&device, &queue),
+            false => None
+            };
 
-        let camera = Camera::new(
-            [0.0, 1.0, 2.0],
-            [0.0; 3],
-            config.width as f32 / config.height as f32,
-            45.0,
-            0.1,
-            100.0
-            );
+        let upscaler = Self::create_upscaler(&device, &config, settings.virtual_resolution, render_scale);
+
+        let (render_width, render_height) = match &upscaler {
+            Some(upscaler) => upscaler.get_size(),
+            None => (config.width, config.height)
+            };
+        let aspect = render_width as f32 / render_height as f32;
+        let camera = scene_description.create_camera(aspect);
 
         let mut camera_uniform = CameraUniform::new();
         camera_uniform.update_view_projection(&camera);
@@ -221,7 +325,68 @@ impl State {
                 ]
             });
 
-        let camera_controller = CameraController::new(0.25);
+        let light_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: cast_slice(&[LightUniform::new(scene.get_lights())]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST
+            });
+
+        let light_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Light Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                        },
+                    count: None
+                    }
+                ]
+            });
+
+        let light_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Light Bind Group"),
+            layout: &light_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: light_buffer.as_entire_binding()
+                    }
+                ]
+            });
+
+        let debug_draw = DebugDraw::new(&device, config.format, &camera_bind_group_layout);
+        let debug_view_renderer = DebugViewRenderer::new(&device, config.format, &camera_bind_group_layout);
+        // Compute shaders are missing on some downlevel backends, like WebGL
+        let downlevel_flags = adapter.get_downlevel_capabilities().flags;
+        let culling = match downlevel_flags.contains(DownlevelFlags::COMPUTE_SHADERS | DownlevelFlags::INDIRECT_EXECUTION) {
+            true => Some(GpuCulling::new(&device, &scene)),
+            false => {
+                info!("Indirect drawing is not supported, instances are not culled");
+                None
+                }
+            };
+        let particle_backend = match downlevel_flags.contains(DownlevelFlags::COMPUTE_SHADERS) {
+            true => ParticleBackend::Gpu,
+            false => {
+                info!("Compute shaders are not supported, particles are simulated on the CPU");
+                ParticleBackend::Cpu
+                }
+            };
+        let mut particles = ParticleSystem::new(&device, &queue, config.format, &camera_bind_group_layout, particle_backend);
+        particles.set_emitters(scene.get_emitters());
+        let picker = Picker::new(&device, config.format, &camera_bind_group_layout, render_width, render_height);
+
+        let CameraDescription { eye, target, .. } = scene_description.camera;
+        let camera_controllers: Vec<Box<dyn CameraController>> = vec![
+            Box::new(OrbitController::new(5.0, 0.25, 10.0)),
+            Box::new(FreeFlyController::new(5.0, 1.5, 0.25, 10.0)),
+            Box::new(FixedController::new(eye, target)),
+            Box::new(ScriptedController::circle([0.0; 3], 6.0, 3.0, 8, Duration::from_secs_f32(1.5)))
+            ];
 
         let shader = device.create_shader_module(include_wgsl!("../shaders/shader.wgsl"));
 
@@ -229,7 +394,8 @@ impl State {
             label: Some("Render Pipeline Layout"),
             bind_group_layouts: &[
                 &texture_bind_group_layout,
-                &camera_bind_group_layout
+                &camera_bind_group_layout,
+                &light_bind_group_layout
                 ],
             push_constant_ranges: &[]
             });
@@ -263,7 +429,7 @@ impl State {
                 strip_index_format: None,
                 front_face: FrontFace::Ccw,
                 cull_mode: Some(Face::Back),
-                // Other modes require Features::NON_FILL_POLYGON_MODE
+                // Other modes require Features::POLYGON_MODE_LINE
                 polygon_mode: PolygonMode::Fill,
                 // Other option requires Features::DEPTH_CLPI_CONTROL
                 unclipped_depth: false,
@@ -286,106 +452,230 @@ impl State {
             cache: None
             });
 
-        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: cast_slice(VERTICES),
-            usage: BufferUsages::VERTEX
-            });
-
-        let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: cast_slice(INDICES),
-            usage: BufferUsages::INDEX
-            });
-
-        const NUM_INSTANCE_PER_ROW: u32 = 8;
-        const INSTANCE_DISPLACEMENT: Vector3<f32> = Vector3::new(
-            NUM_INSTANCE_PER_ROW as f32 * 0.5,
-            0.0,
-            NUM_INSTANCE_PER_ROW as f32 * 0.5
-            );
-
-        let instances: Vec<_> = (0 .. NUM_INSTANCE_PER_ROW * NUM_INSTANCE_PER_ROW)
-            .map(|i| {
-                let (x, z) = (
-                    i % NUM_INSTANCE_PER_ROW,
-                    i / NUM_INSTANCE_PER_ROW
-                    ); 
-                
-                let position = Vector3::new(x as f32, 0.0, z as f32) - INSTANCE_DISPLACEMENT;
-
-                // Check at Zero point as Quaternions can affect scale, if not used properly
-                let rotation = match position.is_zero() {
-                    true => Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0)),
-                    false => Quaternion::from_axis_angle(position.normalize(), Deg(45.0))
-                    };
-
-                ModelInstance::new(position, rotation)
-                })
-            .collect();
-
-        let instances_data: Vec<_> = instances.iter()
-            .map(ModelInstance::to_raw)
-            .collect();
-
-        let instance_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Instance Buffer"),
-            contents: cast_slice(&instances_data),
-            usage: BufferUsages::VERTEX
-            });
-
         Ok(Self {
             window,
+            proxy,
             surface,
             device,
             queue,
             config,
+            present_modes: surface_caps.present_modes,
             render_pipeline,
-            vertex_buffer,
-            index_buffer,
-            num_indices: INDICES.len() as u32,
-            diffuse_bind_group,
-            diffuse_texture,
+            assets,
+            scene,
+            scene_path,
+            culling,
             depth_texture,
+            upscaler,
+            virtual_resolution: settings.virtual_resolution,
+            render_scale,
+            scale_factor,
+            previous_camera: camera.clone(),
             camera,
             camera_uniform,
             camera_buffer,
             camera_bind_group,
-            camera_controller,
-            instances,
-            instance_buffer,
+            camera_controllers,
+            active_controller: 0,
+            cinematic: Cinematic::new(CameraPath::default()),
+            last_update: Instant::now(),
+            light_buffer,
+            light_bind_group,
+            timestep: Duration::from_secs_f64(1.0 / settings.update_rate.max(1) as f64),
+            max_update_steps: settings.max_update_steps.max(1),
+            accumulator: Duration::ZERO,
+            redraw_mode: settings.redraw_mode,
+            min_frame_time: settings.frame_cap
+                .filter(|&cap| cap > 0)
+                .map(|cap| Duration::from_secs_f64(1.0 / cap as f64)),
+            last_frame: Instant::now(),
+            is_redraw_requested: true,
+            is_paused_when_unfocused: settings.is_paused_when_unfocused,
+            is_focused: true,
+            is_occluded: false,
+            key_bindings,
+            stats: settings.stats_interval
+                .filter(|&interval| interval > 0.0)
+                .map(|interval| FrameStats::new(Duration::from_secs_f32(interval), STATS_SAMPLES)),
+            stats_output: settings.stats_output,
+            profiler,
+            text,
+            debug_draw,
+            is_debug_drawing: false,
+            debug_view: DebugView::Shaded,
+            debug_view_renderer,
+            particles,
+            picker,
+            cursor_position: None,
+            pending_pick: None,
+            selected_instance: None,
+            scene_generation: 0,
+            stats_text: String::new(),
+            title: settings.title,
+            pending_screenshot: None,
             is_surface_configured: false
             })
         }
 
-    pub fn update(&mut self) {
-        self.camera_controller.update_camera(&mut self.camera);
-        self.camera_uniform.update_view_projection(&self.camera);
-        self.queue.write_buffer(&self.camera_buffer, 0, cast_slice(&[self.camera_uniform]));
+    // Runs the simulation in fixed steps, and returns how far the leftover time is into the next one
+    pub fn update(&mut self) -> f32 {
+        if let Some(stats) = &mut self.stats {
+            stats.begin_frame();
+            }
+
+        self.assets.free_unused();
+        let now = Instant::now();
+        self.accumulator += now - self.last_update;
+        self.last_update = now;
+
+        let mut steps = 0;
+        while self.accumulator >= self.timestep {
+            // Slow frames would otherwise need ever more steps to catch up
+            if steps == self.max_update_steps {
+                debug!("Dropped {:?} of simulation lag", self.accumulator);
+                self.accumulator = Duration::ZERO;
+                break;
+                }
+
+            self.fixed_update(self.timestep);
+            self.accumulator -= self.timestep;
+            steps += 1;
+            }
+
+        if self.is_debug_drawing {
+            self.draw_gizmos();
+            }
+
+        self.accumulator.div_duration_f32(self.timestep)
+        }
+
+    // Instance transforms, and the camera target
+    fn draw_gizmos(&mut self) {
+        self.debug_draw
+            .set_on_top(false)
+            .grid(Point3::origin(), 10.0, 10, [0.3, 0.3, 0.3]);
+
+        for instance in self.scene.get_instances() {
+            self.debug_draw.axes(Point3::from_vec(instance.get_position()), instance.get_rotation(), 0.5);
+            }
+
+        self.debug_draw
+            .set_on_top(true)
+            .sphere(self.camera.get_target(), 0.1, [1.0, 1.0, 0.0])
+            .set_on_top(false);
+        }
+
+    fn fixed_update(&mut self, dt: Duration) {
+        self.previous_camera = self.camera.clone();
+
+        // Cinematic playback takes over the camera, until it finishes
+        match self.cinematic.is_playing() {
+            true => self.cinematic.update(&mut self.camera, dt),
+            false => self.camera_controllers[self.active_controller].update_camera(&mut self.camera, dt)
+            };
+
+        self.particles.advance(dt);
+        }
+
+    // Called once the event loop runs out of events, decides when the next frame gets drawn
+    pub fn schedule_redraw(&mut self, event_loop: &ActiveEventLoop) {
+        let is_paused = self.is_occluded || self.is_paused_when_unfocused && ! self.is_focused;
+        let is_needed = match self.redraw_mode {
+            RedrawMode::Continuous => true,
+            RedrawMode::OnDemand => self.is_redraw_requested || self.is_animating()
+            };
+
+        if is_paused || ! is_needed {
+            // Time spent idle would otherwise be simulated all at once in the next update
+            self.last_update = Instant::now();
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+            }
+
+        match self.min_frame_time.map(|time| self.last_frame + time) {
+            Some(next_frame) if next_frame > Instant::now() =>
+                event_loop.set_control_flow(ControlFlow::WaitUntil(next_frame)),
+            _ => {
+                event_loop.set_control_flow(ControlFlow::Wait);
+                self.window.request_redraw();
+                }
+            };
+        }
+
+    // Only matters for RedrawMode::OnDemand, as otherwise frames are drawn all the time
+    pub const fn request_redraw(&mut self) {
+        self.is_redraw_requested = true;
+        }
+
+    pub fn is_animating(&self) -> bool {
+        self.cinematic.is_playing()
+            || self.camera_controllers[self.active_controller].is_animating()
+            || ! self.particles.get_emitters().is_empty()
         }
 
-    pub fn render(&mut self) -> Result<(), SurfaceError> {
-        self.window.request_redraw();
+    pub const fn set_focused(&mut self, is_focused: bool) {
+        self.is_focused = is_focused;
+        self.is_redraw_requested = true;
+        }
+
+    pub const fn set_occluded(&mut self, is_occluded: bool) {
+        self.is_occluded = is_occluded;
+        self.is_redraw_requested = true;
+        }
 
-        // Can't render untill the surface is ready
+    // Interpolation is the progress between the last two simulation steps
+    pub fn render(&mut self, interpolation: f32) -> Result<(), SurfaceError> {
+        self.last_frame = Instant::now();
+        self.is_redraw_requested = false;
+
+        let camera = self.previous_camera.lerp(&self.camera, interpolation.clamp(0.0, 1.0));
+        self.camera_uniform.update_view_projection(&camera);
+        self.queue.write_buffer(&self.camera_buffer, 0, cast_slice(&[self.camera_uniform]));
+
+        // Can't render until the surface is ready, the shapes of the skipped frame would pile up otherwise
         if ! self.is_surface_configured {
+            self.debug_draw.clear();
             return Ok(());
             }
 
-        let output = self.surface.get_current_texture()?;
+        // Blocks until the presentation engine hands out a texture, which is not part of the CPU time
+        let acquire_start = Instant::now();
+        let output = match self.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(e) => {
+                self.debug_draw.clear();
+                return Err(e);
+                }
+            };
+        if let Some(stats) = &mut self.stats {
+            stats.exclude(acquire_start.elapsed());
+            }
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.begin_frame();
+            }
 
         let view = output.texture.create_view(&TextureViewDescriptor::default());
 
         let mut encoder = self.device.create_command_encoder(&CommandEncoderDescriptor {
-            label: Some("Render Enocder")
+            label: Some("Render Encoder")
             });
 
+        self.particles.prepare(&self.queue, &mut encoder, &camera, self.profiler.as_mut());
+
+        if let Some(culling) = &self.culling {
+            culling.prepare(&self.queue, &mut encoder, &self.scene, &self.assets, &camera, self.profiler.as_mut());
+            }
+
         /* A mutable borrow of encoder needs to be dropped */ {
             let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[
                     Some(RenderPassColorAttachment {
-                        view: &view,
+                        view: match &self.upscaler {
+                            Some(upscaler) => upscaler.get_color_view(),
+                            None => &view
+                            },
                         resolve_target: None,
                         ops: Operations {
                             load: LoadOp::Clear(Color::BLACK),
@@ -395,7 +685,10 @@ impl State {
                         })
                     ],
                 depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
-                    view: self.depth_texture.get_view(),
+                    view: match &self.upscaler {
+                        Some(upscaler) => upscaler.get_depth_view(),
+                        None => self.depth_texture.get_view()
+                        },
                     depth_ops: Some(Operations {
                         load: LoadOp::Clear(1.0),
                         store: StoreOp::Store
@@ -403,21 +696,151 @@ impl State {
                     stencil_ops: None
                     }),
                 occlusion_query_set: None,
-                timestamp_writes: None
+                timestamp_writes: self.profiler.as_mut()
+                    .and_then(|profiler| profiler.pass_timestamps("Main"))
                 });
             
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
-            render_pass.draw_indexed(0 .. self.num_indices, 0, 0 .. self.instances.len() as u32);
+            if self.debug_view.is_shaded() {
+                render_pass.set_pipeline(&self.render_pipeline);
+                render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+                render_pass.set_bind_group(2, &self.light_bind_group, &[]);
+                match self.culling.as_ref().and_then(GpuCulling::get_buffers) {
+                    Some((instance_buffer, indirect_buffer)) =>
+                        self.scene.draw_indirect(&mut render_pass, &self.assets, instance_buffer, indirect_buffer),
+                    None =>
+                        self.scene.draw(&mut render_pass, &self.assets)
+                    };
+                }
+            self.debug_view_renderer.draw(self.debug_view, &mut render_pass, &self.camera_bind_group, &self.scene, &self.assets);
+
+            if let Some(selected) = self.selected_instance {
+                self.picker.draw_highlight(&mut render_pass, &self.camera_bind_group, &self.scene, &self.assets, selected);
+                }
+
+            // Transparent, so it comes after everything which writes depth
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            self.particles.draw(&mut render_pass);
             }
 
+        let pick = self.pending_pick.take()
+            .map(|position| {
+                let timestamp_writes = self.profiler.as_mut()
+                    .and_then(|profiler| profiler.pass_timestamps("Picking"));
+                self.picker.pick(&mut encoder, &self.camera_bind_group, &self.scene, &self.assets, position, timestamp_writes)
+                });
+
+        self.debug_draw.prepare(&self.device, &self.queue);
+
+        /* A mutable borrow of encoder needs to be dropped */ {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Debug Pass"),
+                color_attachments: &[
+                    Some(RenderPassColorAttachment {
+                        view: match &self.upscaler {
+                            Some(upscaler) => upscaler.get_color_view(),
+                            None => &view
+                            },
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Load,
+                            store: StoreOp::Store
+                            },
+                        depth_slice: None
+                        })
+                    ],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: match &self.upscaler {
+                        Some(upscaler) => upscaler.get_depth_view(),
+                        None => self.depth_texture.get_view()
+                        },
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Store
+                        }),
+                    stencil_ops: None
+                    }),
+                occlusion_query_set: None,
+                timestamp_writes: self.profiler.as_mut()
+                    .and_then(|profiler| profiler.pass_timestamps("Debug"))
+                });
+
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            self.debug_draw.draw(&mut render_pass);
+            }
+
+        if let Some(upscaler) = &self.upscaler {
+            let timestamp_writes = self.profiler.as_mut()
+                .and_then(|profiler| profiler.pass_timestamps("Upscale"));
+            upscaler.blit(&mut encoder, &view, self.config.width, self.config.height, timestamp_writes);
+            }
+
+        // Text is drawn at the window's resolution, so it stays sharp with upscaling
+        self.text.queue_text(&self.stats_text, [4.0, 4.0], self.scale_factor.round().max(1.0) as u32, [1.0, 1.0, 1.0, 1.0]);
+        self.text.prepare(&self.device, &self.queue, self.config.width, self.config.height);
+
+        /* A mutable borrow of encoder needs to be dropped */ {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Text Pass"),
+                color_attachments: &[
+                    Some(RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Load,
+                            store: StoreOp::Store
+                            },
+                        depth_slice: None
+                        })
+                    ],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: self.profiler.as_mut()
+                    .and_then(|profiler| profiler.pass_timestamps("Text"))
+                });
+
+            self.text.draw(&mut render_pass);
+            }
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.resolve(&mut encoder);
+            }
+
+        let screenshot = self.pending_screenshot.take()
+            .map(|path| Screenshot::new(&self.device, &mut encoder, &output.texture, path));
+
         self.queue.submit(once(encoder.finish()));
         output.present();
 
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end_frame();
+            }
+
+        if let Some(screenshot) = screenshot {
+            screenshot.save_in_background(self.device.clone());
+            }
+
+        if let Some(pick) = pick {
+            pick.read_in_background(self.device.clone(), self.proxy.clone(), self.scene_generation);
+            }
+
+        if let Some(report) = self.stats.as_mut().and_then(FrameStats::end_frame) {
+            let gpu_times = self.profiler.iter()
+                .flat_map(GpuProfiler::get_pass_times)
+                .map(|(name, time)| format!("{name} {time:.2} ms"))
+                .collect::<Vec<_>>();
+
+            let text = match gpu_times.is_empty() {
+                true => report.to_string(),
+                false => format!("{report}, GPU: {}", gpu_times.join(", "))
+                };
+
+            match self.stats_output {
+                StatsOutput::Title => self.window.set_title(&format!("{} - {text}", self.title)),
+                StatsOutput::Log => info!("{text}"),
+                StatsOutput::Overlay => self.stats_text = text
+                };
+            }
+
         Ok(())
         }
 
@@ -426,26 +849,254 @@ impl State {
             self.config.width = width;
             self.config.height = height;
             self.surface.configure(&self.device, &self.config);
-            self.depth_texture = Texture::create_depth_texture(&self.device, &self.config, Some("Depth Texture"));
+            self.depth_texture = Texture::create_depth_texture(&self.device, width, height, Some("Depth Texture"));
+            self.upscaler = Self::create_upscaler(&self.device, &self.config, self.virtual_resolution, self.render_scale);
+            let (render_width, render_height) = self.get_render_size();
+            self.picker.resize(&self.device, render_width, render_height);
             self.is_surface_configured = true;
+            self.is_redraw_requested = true;
+
+            // Both cameras, so the image doesn't get stretched until the next update
+            let aspect = self.get_aspect();
+            self.camera.set_aspect(aspect);
+            self.previous_camera.set_aspect(aspect);
             }
         }
 
+    // Unsupported modes are only logged, keeping the current one
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) -> bool {
+        if ! is_present_mode_supported(&self.present_modes, present_mode) {
+            warn!("Present mode {present_mode:?} is not supported by the surface");
+            return false;
+            }
+
+        self.config.present_mode = present_mode;
+        if self.is_surface_configured {
+            self.surface.configure(&self.device, &self.config);
+            }
+
+        info!("Present mode set to {present_mode:?}");
+        true
+        }
+
+    pub const fn get_present_mode(&self) -> PresentMode {
+        self.config.present_mode
+        }
+
+    // Returns the physical size which keeps the window's logical size on the new display
+    pub fn set_scale_factor(&mut self, scale_factor: f64) -> PhysicalSize<u32> {
+        let logical: LogicalSize<f64> = PhysicalSize::new(self.config.width, self.config.height)
+            .to_logical(self.scale_factor);
+
+        info!("Scale factor changed from {} to {scale_factor}", self.scale_factor);
+        self.scale_factor = scale_factor;
+
+        logical.to_physical(scale_factor)
+        }
+
+    pub const fn get_scale_factor(&self) -> f64 {
+        self.scale_factor
+        }
+
     pub fn handle_key(&mut self, event_loop: &ActiveEventLoop, code: KeyCode, is_pressed: bool) {
-        self.camera_controller.handle_key(code, is_pressed);
+        self.is_redraw_requested = true;
+        self.camera_controllers[self.active_controller].handle_key(code, is_pressed);
         
         if ! is_pressed {
             return;
             }
 
-        match code {
-            KeyCode::Escape =>
+        match self.key_bindings.get_action(code) {
+            Some(Action::Exit) =>
                 event_loop.exit(),
-            KeyCode::KeyF =>
+            Some(Action::Fullscreen) =>
                 self.set_fullscreen(true),
-            KeyCode::KeyE =>
-                self.set_fullscreen(false),    
-            _ => ()
+            Some(Action::Windowed) =>
+                self.set_fullscreen(false),
+            // Overwrites the loaded scene file, or creates a new one in the working directory
+            Some(Action::SaveScene) => {
+                let path = self.scene_path.clone()
+                    .unwrap_or_else(|| PathBuf::from("scene.ron"));
+                self.save_scene(&path);
+                },
+            Some(Action::Screenshot) => {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_millis())
+                    .unwrap_or_default();
+                self.request_screenshot(PathBuf::from(format!("screenshot-{timestamp}.png")));
+                },
+            Some(Action::NextCameraController) => {
+                self.camera_controllers[self.active_controller].reset();
+                self.active_controller = (self.active_controller + 1) % self.camera_controllers.len();
+                info!("Switched to the {} camera", self.camera_controllers[self.active_controller].get_name());
+                },
+            Some(Action::RecordKeyframe) => {
+                let keyframe = self.cinematic.record_keyframe(&self.camera, 2.0);
+                info!("Recorded a keyframe at {:.1}s", keyframe.time);
+                },
+            Some(Action::TogglePlayback) =>
+                self.cinematic.toggle_playback(&mut self.camera),
+            Some(Action::RestartPlayback) =>
+                self.cinematic.restart(),
+            Some(Action::SlowerPlayback) =>
+                self.cinematic.set_speed(self.cinematic.get_speed() * 0.5),
+            Some(Action::FasterPlayback) =>
+                self.cinematic.set_speed(self.cinematic.get_speed() * 2.0),
+            Some(Action::SaveCameraPath) => {
+                let path = Path::new("camera-path.ron");
+                match self.cinematic.get_path().save(path) {
+                    Ok(_) => info!("Saved the camera path to {}", path.display()),
+                    Err(e) => error!("Unable to save the camera path {e:#}")
+                    };
+                },
+            Some(Action::ListAssets) => {
+                for info in self.assets.list() {
+                    info!("{info}");
+                    }
+                info!("Total GPU memory used by assets: {:.1} KiB", self.assets.get_memory_size() as f64 / 1024.0);
+                },
+            Some(Action::NextDebugView) => {
+                self.debug_view = self.debug_view.next();
+                info!("Debug view set to {:?}", self.debug_view);
+                },
+            Some(Action::ToggleDebugDraw) =>
+                self.is_debug_drawing = ! self.is_debug_drawing,
+            Some(Action::ExportStats) => {
+                let path = Path::new("frame-stats.csv");
+                match &self.stats {
+                    Some(stats) => match stats.save_csv(path) {
+                        Ok(_) => info!("Saved {} frame stats reports to {}", stats.get_reports().len(), path.display()),
+                        Err(e) => error!("Unable to save the frame stats {e:#}")
+                        },
+                    None => warn!("Frame stats are disabled")
+                    };
+                },
+            Some(Action::NextPresentMode) => {
+                let index = PRESENT_MODES.iter()
+                    .position(|&mode| mode == self.config.present_mode)
+                    .map_or(0, |index| index + 1);
+
+                // Unsupported modes are skipped, and Fifo is always available
+                let present_mode = (index .. index + PRESENT_MODES.len())
+                    .map(|i| PRESENT_MODES[i % PRESENT_MODES.len()])
+                    .find(|&mode| is_present_mode_supported(&self.present_modes, mode))
+                    .unwrap_or(PresentMode::Fifo);
+                self.set_present_mode(present_mode);
+                },
+            None => ()
+            };
+        }
+
+    pub fn handle_event(&mut self, event: AppEvent) {
+        self.is_redraw_requested = true;
+        match event {
+            AppEvent::AssetLoaded(loaded) =>
+                self.assets.finish_load(loaded),
+            AppEvent::AssetChanged(path) => {
+                if let Some(scene_path) = self.scene_path.clone().filter(|_| self.is_scene_file(&path)) {
+                    info!("Reloading the scene {}", scene_path.display());
+                    self.load_scene(scene_path);
+                    }
+                self.assets.reload(&path);
+                },
+            AppEvent::LoadScene(path) =>
+                self.load_scene(path),
+            AppEvent::SceneLoaded { path, description } =>
+                self.replace_scene(path, &description),
+            AppEvent::SaveScene(path) =>
+                self.save_scene(&path),
+            AppEvent::LoadCameraPath(path) => match CameraPath::load(&path) {
+                Ok(camera_path) => self.cinematic.set_path(camera_path, &mut self.camera),
+                Err(e) => error!("Unable to load the camera path {e:#}")
+                },
+            AppEvent::Screenshot(path) =>
+                self.request_screenshot(path),
+            AppEvent::InstancePicked { generation, .. } if generation != self.scene_generation =>
+                info!("Dropped a pick of a replaced scene"),
+            AppEvent::InstancePicked { slot: Some(slot), .. } => match self.scene.get_file_index(slot) {
+                Some(index) => {
+                    info!("Selected instance {index}");
+                    self.selected_instance = Some(slot);
+                    },
+                None => warn!("Picked the unknown instance slot {slot}")
+                },
+            AppEvent::InstancePicked { slot: None, .. } => {
+                info!("Cleared the selection");
+                self.selected_instance = None;
+                },
+            AppEvent::Shutdown =>
+                warn!("Shutdown has to be handled by the application")
+            };
+        }
+
+    // Reading, and parsing of the file happens in the background, the result comes back as AppEvent::SceneLoaded
+    fn load_scene(&self, path: PathBuf) {
+        let proxy = self.proxy.clone();
+        thread::spawn(move || {
+            match SceneDescription::load(&path) {
+                Ok(description) => {
+                    if proxy.send_event(AppEvent::SceneLoaded { path, description }).is_err() {
+                        warn!("Event loop was closed before the scene was loaded");
+                        }
+                    },
+                Err(e) => error!("Unable to load the scene {e:#}")
+                };
+            });
+        }
+
+    fn replace_scene(&mut self, path: PathBuf, description: &SceneDescription) {
+        match Scene::new(&self.device, &mut self.assets, description) {
+            Ok(scene) => {
+                self.scene = scene;
+                self.queue.write_buffer(&self.light_buffer, 0, cast_slice(&[LightUniform::new(self.scene.get_lights())]));
+                // Indices of the old instances mean nothing in the new scene
+                self.selected_instance = None;
+                self.scene_generation += 1;
+                self.particles.set_emitters(self.scene.get_emitters());
+                if let Some(culling) = &mut self.culling {
+                    culling.set_scene(&self.scene);
+                    }
+                self.camera = description.create_camera(self.get_aspect());
+                // Nothing to blend from, as the camera jumps to the new scene
+                self.previous_camera = self.camera.clone();
+                let CameraDescription { eye, target, .. } = description.camera;
+                // Only the built in one, which comes before the ones added by the library users
+                if let Some(fixed) = self.camera_controllers.iter_mut()
+                    .find_map(|controller| (controller.as_mut() as &mut dyn Any).downcast_mut::<FixedController>()) {
+                    fixed.set_view(eye, target);
+                    }
+                info!("Loaded the scene from {}", path.display());
+                self.scene_path = Some(path);
+                },
+            Err(e) => error!("Unable to create the scene {e:#}")
+            };
+        }
+
+    // Whether a path reported by the hot reload, relative to the assets directory, is the current scene's file
+    fn is_scene_file(&self, path: &Path) -> bool {
+        let (Some(dir), Some(scene_path)) = (self.assets.get_directory(), &self.scene_path) else {
+            return false;
+            };
+
+        match (dir.join(path).canonicalize(), scene_path.canonicalize()) {
+            (Ok(changed), Ok(scene)) => changed == scene,
+            _ => false
+            }
+        }
+
+    fn save_scene(&self, path: &Path) {
+        match self.scene.to_description(&self.camera, &self.assets).save(path) {
+            Ok(_) => info!("Saved the scene to {}", path.display()),
+            Err(e) => error!("Unable to save the scene {e:#}")
+            };
+        }
+
+    // The capture is taken from the next rendered frame
+    fn request_screenshot(&mut self, path: PathBuf) {
+        match self.config.usage.contains(TextureUsages::COPY_SRC) {
+            true => self.pending_screenshot = Some(path),
+            false => warn!("Surface does not support screenshots")
             };
         }
 
@@ -456,7 +1107,128 @@ impl State {
             });
         }
 
+    // A virtual resolution renders at a fixed size, while a render scale follows the window
+    fn create_upscaler(device: &Device, config: &SurfaceConfiguration, virtual_resolution: Option<(u32, u32)>, render_scale: f32) -> Option<Upscaler> {
+        match virtual_resolution {
+            Some((width, height)) => Some(Upscaler::new(device, config.format, width.max(1), height.max(1), true)),
+            None if render_scale != 1.0 => {
+                let width = (config.width as f32 * render_scale).round().max(1.0) as u32;
+                let height = (config.height as f32 * render_scale).round().max(1.0) as u32;
+                Some(Upscaler::new(device, config.format, width, height, false))
+                },
+            None => None
+            }
+        }
+
+    // Size of the target the scene is rendered to, before upscaling
+    fn get_render_size(&self) -> (u32, u32) {
+        match &self.upscaler {
+            Some(upscaler) => upscaler.get_size(),
+            None => (self.config.width, self.config.height)
+            }
+        }
+
+    // The virtual resolution keeps its aspect no matter the window size
+    fn get_aspect(&self) -> f32 {
+        let (width, height) = self.get_render_size();
+        width as f32 / height as f32
+        }
+
+    pub const fn set_cursor_position(&mut self, position: Option<PhysicalPosition<f64>>) {
+        self.cursor_position = position;
+        }
+
+    // Window pixels are mapped by the upscaler onto a smaller, or letterboxed target, None on the letterbox bars
+    fn to_render_position(&self, PhysicalPosition { x, y }: PhysicalPosition<f64>) -> Option<Vec2<f32>> {
+        let (render_width, render_height) = self.get_render_size();
+        let (offset_x, offset_y, width, height) = match &self.upscaler {
+            Some(upscaler) => upscaler.get_viewport(self.config.width, self.config.height),
+            None => (0.0, 0.0, self.config.width as f32, self.config.height as f32)
+            };
+        let x = (x as f32 - offset_x) / width * render_width as f32;
+        let y = (y as f32 - offset_y) / height * render_height as f32;
+
+        match x >= 0.0 && y >= 0.0 && x < render_width as f32 && y < render_height as f32 {
+            true => Some([x, y]),
+            false => None
+            }
+        }
+
+    // Picks the instance under the cursor during the next render, the result comes back as AppEvent::InstancePicked
+    pub fn request_pick(&mut self) {
+        let Some(position) = self.cursor_position else {
+            return;
+            };
+
+        match self.to_render_position(position) {
+            Some([x, y]) => {
+                self.pending_pick = Some((x as u32, y as u32));
+                self.is_redraw_requested = true;
+                },
+            None => {
+                let _ = self.proxy.send_event(AppEvent::InstancePicked { slot: None, generation: self.scene_generation });
+                }
+            };
+        }
+
+    // Casts on the CPU against the current camera, unlike picking the result is available right away
+    pub fn raycast(&self, position: PhysicalPosition<f64>) -> Option<RayHit> {
+        let position = self.to_render_position(position)?;
+        let (width, height) = self.get_render_size();
+        let ray = self.camera.screen_ray(position, [width as f32, height as f32]);
+
+        self.scene.raycast(&ray, &self.assets)
+        }
+
+    // Position of the selected instance inside of the scene file
+    pub fn get_selected_instance(&self) -> Option<usize> {
+        self.selected_instance
+            .and_then(|slot| self.scene.get_file_index(slot))
+        }
+
+    // Shapes are drawn during the next render
+    pub const fn get_debug_draw_mut(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw
+        }
+
+    // Emitters are replaced by the ones of every loaded scene
+    pub const fn get_particles_mut(&mut self) -> &mut ParticleSystem {
+        &mut self.particles
+        }
+
+    // Queued text is drawn during the next render
+    pub const fn get_text_mut(&mut self) -> &mut TextRenderer {
+        &mut self.text
+        }
+
     pub fn get_window(&self) -> &Window {
         &self.window
         }
+
+    pub const fn get_assets(&self) -> &Assets {
+        &self.assets
+        }
+
+    pub const fn get_scene(&self) -> &Scene {
+        &self.scene
+        }
+
+    pub const fn get_camera(&self) -> &Camera {
+        &self.camera
+        }
+
+    // Controllers can be added by the library users, and cycled through with Action::NextCameraController
+    pub fn add_camera_controller(&mut self, controller: Box<dyn CameraController>) {
+        self.camera_controllers.push(controller);
+        }
+
+    pub const fn get_key_bindings_mut(&mut self) -> &mut KeyBindings {
+        &mut self.key_bindings
+        }
+    }
+
+// Automatic modes are resolved by wgpu, so they work on every surface
+fn is_present_mode_supported(supported: &[PresentMode], present_mode: PresentMode) -> bool {
+    matches!(present_mode, PresentMode::AutoVsync | PresentMode::AutoNoVsync)
+        || supported.contains(&present_mode)
     }
\ No newline at end of file
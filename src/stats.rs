@@ -0,0 +1,264 @@
+use {
+    anyhow::{
+        Context,
+        Result as DynResult
+        },
+    serde::{
+        Deserialize,
+        Serialize
+        },
+    std::{
+        collections::VecDeque,
+        fmt::{
+            self,
+            Display,
+            Formatter,
+            Write as _
+            },
+        fs,
+        path::Path,
+        time::{
+            Duration,
+            Instant
+            }
+        }
+    };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum StatsOutput {
+    #[default]
+    Title,
+    Log,
+    // Drawn as text in the corner of the window
+    Overlay
+    }
+
+// Summary of the frames since the previous report, times are in milliseconds
+#[derive(Debug, Clone, Copy)]
+pub struct StatsReport {
+    // In seconds since the start of the measurement
+    pub time: f32,
+    pub fps: f32,
+    pub average: f32,
+    pub min: f32,
+    pub max: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32
+    }
+
+// Measures the CPU time spent on each frame, and summarizes it at a fixed interval
+pub struct FrameStats {
+    interval: Duration,
+    // Rolling window of the most recent frame times
+    samples: VecDeque<Duration>,
+    max_samples: usize,
+    started: Instant,
+    frame_start: Option<Instant>,
+    // Time the current frame spent blocked, which is not CPU work
+    blocked: Duration,
+    last_report: Instant,
+    frames_since_report: u32,
+    // Every report so far, kept for the CSV export
+    reports: Vec<StatsReport>
+    }
+
+impl Display for StatsReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{:.0} FPS, {:.2} ms (min {:.2}, max {:.2}, p95 {:.2}, p99 {:.2})",
+            self.fps, self.average, self.min, self.max, self.p95, self.p99)
+        }
+    }
+
+impl FrameStats {
+    pub fn new(interval: Duration, max_samples: usize) -> Self {
+        let now = Instant::now();
+        Self {
+            interval,
+            samples: VecDeque::with_capacity(max_samples),
+            max_samples: max_samples.max(1),
+            started: now,
+            frame_start: None,
+            blocked: Duration::ZERO,
+            last_report: now,
+            frames_since_report: 0,
+            reports: Vec::new()
+            }
+        }
+
+    pub fn begin_frame(&mut self) {
+        self.frame_start = Some(Instant::now());
+        self.blocked = Duration::ZERO;
+        }
+
+    // Leaves out time spent waiting, like for the next surface texture
+    pub fn exclude(&mut self, blocked: Duration) {
+        self.blocked += blocked;
+        }
+
+    // Returns a new report once the interval has passed
+    pub fn end_frame(&mut self) -> Option<StatsReport> {
+        let frame_time = self.frame_start.take()?
+            .elapsed()
+            .saturating_sub(self.blocked);
+
+        if self.samples.len() == self.max_samples {
+            self.samples.pop_front();
+            }
+        self.samples.push_back(frame_time);
+        self.frames_since_report += 1;
+
+        let since_report = self.last_report.elapsed();
+        if since_report < self.interval {
+            return None;
+            }
+
+        let report = self.create_report(since_report);
+        self.last_report = Instant::now();
+        self.frames_since_report = 0;
+        self.reports.push(report);
+
+        Some(report)
+        }
+
+    fn create_report(&self, since_report: Duration) -> StatsReport {
+        let mut sorted: Vec<_> = self.samples.iter()
+            .map(|time| time.as_secs_f32() * 1000.0)
+            .collect();
+        sorted.sort_by(f32::total_cmp);
+
+        // Nearest-rank percentile, an empty history reports zeros
+        let percentile = |p: f32| sorted.get(((p * sorted.len() as f32).ceil() as usize).max(1) - 1)
+            .copied()
+            .unwrap_or_default();
+
+        StatsReport {
+            time: self.started.elapsed().as_secs_f32(),
+            fps: self.frames_since_report as f32 / since_report.as_secs_f32(),
+            average: sorted.iter().sum::<f32>() / sorted.len().max(1) as f32,
+            min: sorted.first().copied().unwrap_or_default(),
+            max: sorted.last().copied().unwrap_or_default(),
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99)
+            }
+        }
+
+    pub fn get_reports(&self) -> &[StatsReport] {
+        &self.reports
+        }
+
+    pub fn save_csv(&self, path: &Path) -> DynResult<()> {
+        fs::write(path, self.to_csv())
+            .with_context(|| format!("Unable to write frame stats {}", path.display()))
+        }
+
+    // Header, and a row for each of the reports
+    fn to_csv(&self) -> String {
+        let mut text = "time,fps,average_ms,min_ms,max_ms,p50_ms,p95_ms,p99_ms\n".to_owned();
+        for report in &self.reports {
+            writeln!(text, "{:.3},{:.2},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}",
+                report.time, report.fps, report.average, report.min, report.max, report.p50, report.p95, report.p99)
+                .expect("Writing to a String can't fail");
+            }
+
+        text
+        }
+    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{a} is not {b}");
+        }
+
+    fn stats_with_samples(milliseconds: impl IntoIterator<Item = u64>) -> FrameStats {
+        let mut stats = FrameStats::new(Duration::from_secs(1), 1000);
+        stats.samples.extend(milliseconds.into_iter().map(Duration::from_millis));
+        stats.frames_since_report = stats.samples.len() as u32;
+        stats
+        }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        // Reversed, as the samples come in any order
+        let stats = stats_with_samples((1 ..= 100).rev());
+        let report = stats.create_report(Duration::from_secs(2));
+
+        assert_near(report.fps, 50.0);
+        assert_near(report.average, 50.5);
+        assert_near(report.min, 1.0);
+        assert_near(report.max, 100.0);
+        assert_near(report.p50, 50.0);
+        assert_near(report.p95, 95.0);
+        assert_near(report.p99, 99.0);
+
+        let report = stats_with_samples([10, 30, 20, 40]).create_report(Duration::from_secs(1));
+        assert_near(report.p50, 20.0);
+        assert_near(report.p95, 40.0);
+        assert_near(report.p99, 40.0);
+        }
+
+    #[test]
+    fn single_samples_are_every_percentile() {
+        let report = stats_with_samples([16]).create_report(Duration::from_secs(1));
+
+        for value in [report.average, report.min, report.max, report.p50, report.p95, report.p99] {
+            assert_near(value, 16.0);
+            }
+        assert_near(report.fps, 1.0);
+        }
+
+    #[test]
+    fn empty_history_reports_zeros() {
+        let mut stats = stats_with_samples([]);
+        // Frames have to begin before they can end
+        assert!(stats.end_frame().is_none());
+        assert!(stats.get_reports().is_empty());
+
+        let report = stats.create_report(Duration::from_secs(1));
+        for value in [report.fps, report.average, report.min, report.max, report.p50, report.p95, report.p99] {
+            assert_eq!(value, 0.0);
+            }
+        }
+
+    #[test]
+    fn rolling_window_drops_the_oldest_samples() {
+        let mut stats = FrameStats::new(Duration::ZERO, 2);
+        for _ in 0 .. 3 {
+            stats.begin_frame();
+            // Waiting longer than the frame took leaves no CPU time
+            stats.exclude(Duration::from_secs(60));
+            assert!(stats.end_frame().is_some());
+            }
+
+        assert_eq!(stats.samples.len(), 2);
+        assert_eq!(stats.get_reports().len(), 3);
+        assert_eq!(stats.get_reports()[2].max, 0.0);
+        }
+
+    #[test]
+    fn csv_has_a_header_and_a_row_per_report() {
+        let mut stats = stats_with_samples([]);
+        assert_eq!(stats.to_csv(), "time,fps,average_ms,min_ms,max_ms,p50_ms,p95_ms,p99_ms\n");
+
+        stats.reports.push(StatsReport {
+            time: 1.5,
+            fps: 59.876,
+            average: 16.7,
+            min: 10.0,
+            max: 33.3333,
+            p50: 16.0,
+            p95: 25.0,
+            p99: 30.125
+            });
+
+        let csv = stats.to_csv();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines, [
+            "time,fps,average_ms,min_ms,max_ms,p50_ms,p95_ms,p99_ms",
+            "1.500,59.88,16.700,10.000,33.333,16.000,25.000,30.125"
+            ]);
+        }
+    }
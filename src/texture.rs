@@ -2,7 +2,7 @@ use {
     image::{
         load_from_memory,
         DynamicImage,
-        GenericImageView
+        RgbaImage
         },
     anyhow::Result as DynResult,
     wgpu::{
@@ -11,6 +11,7 @@ use {
         }
     };
 
+#[derive(Clone)]
 pub struct Texture {
     texture: WGPUTexture,
     view: TextureView,
@@ -21,15 +22,24 @@ impl Texture {
     pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
 
     pub fn from_bytes(device: &Device, queue: &Queue, bytes: &[u8], label: Option<&str>) -> DynResult<Self> {
-        Self::from_image(
-            device,
-            queue,
-            load_from_memory(bytes)?,
-            label
-            )
+        Ok(Self::from_rgba(device, queue, &Self::decode(bytes)?, label))
+        }
+
+    // CPU only half of from_bytes, so it can be done away from the device
+    pub fn decode(bytes: &[u8]) -> DynResult<RgbaImage> {
+        Ok(load_from_memory(bytes)?.into_rgba8())
         }
 
     pub fn from_image(device: &Device, queue: &Queue, img: DynamicImage, label: Option<&str>) -> DynResult<Self> {
+        Ok(Self::from_rgba(device, queue, &img.to_rgba8(), label))
+        }
+
+    // Stand-in for textures which are not loaded yet
+    pub fn placeholder(device: &Device, queue: &Queue) -> Self {
+        Self::from_rgba(device, queue, &RgbaImage::from_pixel(1, 1, [128, 128, 128, 255].into()), Some("Placeholder Texture"))
+        }
+
+    pub fn from_rgba(device: &Device, queue: &Queue, img: &RgbaImage, label: Option<&str>) -> Self {
         let (width, height) = img.dimensions();
 
         let size = Extent3d {
@@ -59,7 +69,7 @@ impl Texture {
                 mip_level: 0,
                 origin: Origin3d::ZERO
                 },
-            &img.to_rgba8(),
+            img,
             TexelCopyBufferLayout {
                 offset: 0,
                 bytes_per_row: Some(4 * width),
@@ -80,13 +90,13 @@ impl Texture {
             .. Default::default()
             });
 
-        Ok(Self { texture, view, sampler })
+        Self { texture, view, sampler }
         }
 
-    pub fn create_depth_texture(device: &Device, config: &SurfaceConfiguration, label: Option<&str>) -> Self {
+    pub fn create_depth_texture(device: &Device, width: u32, height: u32, label: Option<&str>) -> Self {
         let size = Extent3d {
-            width: config.width.max(1),
-            height: config.height.max(1),
+            width: width.max(1),
+            height: height.max(1),
             depth_or_array_layers: 1
             };
 
@@ -119,6 +129,54 @@ impl Texture {
         Self { texture, view, sampler }
         }
 
+    // Offscreen color target, which can be sampled or copied afterwards, the filter decides whether sampled pixels get blended
+    pub fn create_render_target(device: &Device, width: u32, height: u32, format: TextureFormat, filter: FilterMode, label: Option<&str>) -> Self {
+        let size = Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1
+            };
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC,
+            view_formats: &[]
+            });
+
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: FilterMode::Nearest,
+            .. Default::default()
+            });
+
+        Self { texture, view, sampler }
+        }
+
+    // Size of the base level, as no mipmaps are generated
+    pub fn get_memory_size(&self) -> u64 {
+        let Extent3d { width, height, depth_or_array_layers } = self.texture.size();
+        let texel_size = self.texture.format()
+            .block_copy_size(None)
+            .unwrap_or_default();
+
+        width as u64 * height as u64 * depth_or_array_layers as u64 * texel_size as u64
+        }
+
+    pub const fn get_texture(&self) -> &WGPUTexture {
+        &self.texture
+        }
+
     pub const fn get_sampler(&self) -> &Sampler {
         &self.sampler
         }
@@ -0,0 +1,171 @@
+use {
+    wgpu::*,
+    crate::texture::Texture
+    };
+
+// Scene is rendered at a different resolution, and then scaled to the window
+pub struct Upscaler {
+    width: u32,
+    height: u32,
+    // Scales by whole multiples with nearest filtering, otherwise the image is filtered to fit the window
+    is_pixel_perfect: bool,
+    color_texture: Texture,
+    depth_texture: Texture,
+    bind_group: BindGroup,
+    pipeline: RenderPipeline
+    }
+
+impl Upscaler {
+    pub fn new(device: &Device, format: TextureFormat, width: u32, height: u32, is_pixel_perfect: bool) -> Self {
+        let filter = match is_pixel_perfect {
+            true => FilterMode::Nearest,
+            false => FilterMode::Linear
+            };
+
+        let color_texture = Texture::create_render_target(device, width, height, format, filter, Some("Virtual Color Texture"));
+        let depth_texture = Texture::create_depth_texture(device, width, height, Some("Virtual Depth Texture"));
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Upscale Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false
+                        },
+                    count: None
+                    },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None
+                    }
+                ]
+            });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Upscale Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(color_texture.get_view())
+                    },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(color_texture.get_sampler())
+                    }
+                ]
+            });
+
+        let shader = device.create_shader_module(include_wgsl!("../shaders/upscale.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Upscale Pipeline Layout"),
+            bind_group_layouts: &[
+                &bind_group_layout
+                ],
+            push_constant_ranges: &[]
+            });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Upscale Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                // Vertices are generated inside of the shader
+                buffers: &[]
+                },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[
+                    Some(ColorTargetState {
+                        format,
+                        blend: Some(BlendState::REPLACE),
+                        write_mask: ColorWrites::ALL
+                        })
+                    ],
+                compilation_options: PipelineCompilationOptions::default()
+                }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None
+            });
+
+        Self {
+            width,
+            height,
+            is_pixel_perfect,
+            color_texture,
+            depth_texture,
+            bind_group,
+            pipeline
+            }
+        }
+
+    pub const fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+        }
+
+    pub const fn get_color_view(&self) -> &TextureView {
+        self.color_texture.get_view()
+        }
+
+    pub const fn get_depth_view(&self) -> &TextureView {
+        self.depth_texture.get_view()
+        }
+
+    // Returns (x, y, width, height) of the centered image, the rest of the window gets letterboxed
+    pub fn get_viewport(&self, window_width: u32, window_height: u32) -> (f32, f32, f32, f32) {
+        let scale_x = window_width as f32 / self.width as f32;
+        let scale_y = window_height as f32 / self.height as f32;
+
+        // Windows smaller than the virtual resolution can't use whole multiples, so the image is shrunk to fit
+        let scale = match scale_x.min(scale_y) {
+            scale if self.is_pixel_perfect && scale >= 1.0 => scale.floor(),
+            scale => scale
+            };
+
+        let (width, height) = (self.width as f32 * scale, self.height as f32 * scale);
+        let x = ((window_width as f32 - width) * 0.5).floor();
+        let y = ((window_height as f32 - height) * 0.5).floor();
+
+        (x, y, width, height)
+        }
+
+    pub fn blit(&self, encoder: &mut CommandEncoder, target: &TextureView, window_width: u32, window_height: u32, timestamp_writes: Option<RenderPassTimestampWrites>) {
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Upscale Pass"),
+            color_attachments: &[
+                Some(RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: StoreOp::Store
+                        },
+                    depth_slice: None
+                    })
+                ],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes
+            });
+
+        let (x, y, width, height) = self.get_viewport(window_width, window_height);
+
+        render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0 .. 3, 0 .. 1);
+        }
+    }
\ No newline at end of file
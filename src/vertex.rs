@@ -3,35 +3,52 @@ use {
         Pod,
         Zeroable
         },
+    serde::{
+        Deserialize,
+        Serialize
+        },
     wgpu::*,
     std::mem::size_of,
     crate::utils::*
     };
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable, Serialize, Deserialize)]
 pub struct Vertex {
     position: Vec3<f32>,
     texture_coords: Vec2<f32>
     }
 
+// Vertex of a mesh without shared vertices, which knows its corner of the triangle
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
-pub struct ModelVertex {
+pub struct BarycentricVertex {
     position: Vec3<f32>,
     texture_coords: Vec2<f32>,
-    normal_coords: Vec3<f32>
+    barycentric: Vec3<f32>
     }
 
 impl Vertex {
     pub const fn new(position: Vec3<f32>, texture_coords: Vec2<f32>) -> Self {
         Self { position, texture_coords }
         }
+
+    pub const fn get_position(&self) -> Vec3<f32> {
+        self.position
+        }
+
+    pub const fn get_texture_coords(&self) -> Vec2<f32> {
+        self.texture_coords
+        }
     }
 
-impl ModelVertex {
-    pub const fn new(position: Vec3<f32>, texture_coords: Vec2<f32>, normal_coords: Vec3<f32>) -> Self {
-        Self { position, texture_coords, normal_coords }
+impl BarycentricVertex {
+    pub const fn new(vertex: Vertex, barycentric: Vec3<f32>) -> Self {
+        Self {
+            position: vertex.position,
+            texture_coords: vertex.texture_coords,
+            barycentric
+            }
         }
     }
 
@@ -46,7 +63,7 @@ impl VertexInfo for Vertex {
         };
     }
 
-impl VertexInfo for ModelVertex {
+impl VertexInfo for BarycentricVertex {
     const DESC: VertexBufferLayout<'static> = VertexBufferLayout {
         array_stride: size_of::<Self>() as BufferAddress,
         step_mode: VertexStepMode::Vertex,